@@ -2,9 +2,11 @@
 
 use piston::input::GenericEvent;
 use taquin::state::State;
-use taquin::reducer::Reducer;
+use taquin::direction::Direction;
+use taquin::solver::{create_solver, CancelToken, Limits, Outcome};
 use std::collections::VecDeque;
-use time::PreciseTime;
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
 
 
 /// Handles events for Fifteen puzzle game.
@@ -20,7 +22,9 @@ pub struct GameboardController {
     /// Time since last move was made.
     time_since_last_move: f64,
     /// A vector containing the moves needed to finish the game.
-    calculated_moves: VecDeque<(i32, i32)>,
+    calculated_moves: VecDeque<Direction>,
+    /// The search running in the background, if any : its token and its result.
+    search: Option<(CancelToken, Receiver<Outcome<Direction>>)>,
 }
 
 impl GameboardController {
@@ -33,6 +37,7 @@ impl GameboardController {
             solving: false,
             time_since_last_move: 0.0,
             calculated_moves: VecDeque::new(),
+            search: None,
         }
     }
 
@@ -59,49 +64,96 @@ impl GameboardController {
         }
         let mut mv = match e.press_args() {
             Some(Button::Keyboard(key)) => {
+                // the arrows move the empty cell, unless the moves to solve
+                // the board as it is are being searched.
+                let searching = self.search.is_some();
                 match key {
-                    Key::Down => (1, 0),
-                    Key::Up => (-1, 0),
-                    Key::Left => (0, -1),
-                    Key::Right => (0, 1),
+                    Key::Down if !searching => Some(Direction::Down),
+                    Key::Up if !searching => Some(Direction::Up),
+                    Key::Left if !searching => Some(Direction::Left),
+                    Key::Right if !searching => Some(Direction::Right),
                     Key::Space => {
-                        self.solve();
-                        self.solving = !self.solving;
-                        (0, 0)
+                        if let Some((token, _)) = self.search.take() {
+                            token.cancel();
+                            println!("Search cancelled");
+                        } else if self.solving {
+                            self.solving = false;
+                        } else {
+                            self.solve();
+                        }
+                        None
                     }
-                    Key::S => {
-                        self.gameboard.shuffle(5000);
-                        (0, 0)
+                    Key::S if !searching => {
+                        self.gameboard.shuffle(5000, ::time::precise_time_ns());
+                        None
                     }
-                    _ => (0, 0),
+                    _ => None,
                 }
             }
-            _ => (0, 0),
+            _ => None,
         };
+        self.receive_solution();
         if self.solving {
             mv = self.get_next_solved();
-            if mv == (0,0) {
-                self.solving != self.solving;
+            if mv.is_none() {
+                self.solving = false;
             }
         }
-        if self.gameboard.validate(mv) {
-            self.gameboard.modify(mv);
+        if let Some(mv) = mv {
+            if self.gameboard.validate(mv) {
+                self.gameboard.modify(mv);
+            }
         }
     }
-    fn get_next_solved(&mut self) -> (i32, i32) {
+    fn get_next_solved(&mut self) -> Option<Direction> {
         if self.time_since_last_move < 0.0 {
-            return (0, 0);
+            return None;
         } else {
             self.time_since_last_move -= 0.0;
-            self.calculated_moves.pop_front().unwrap_or((0, 0))
+            self.calculated_moves.pop_front()
         }
     }
+    /// Starts searching the moves in the background, so that the game keeps responding.
     fn solve(&mut self) {
-        let start = PreciseTime::now();
-        self.calculated_moves = Reducer::new(self.gameboard.clone()).reduce().unwrap_or(VecDeque::new());
-
-        let end = PreciseTime::now();
-        let diff = start.to(end).num_microseconds();
-        println!("Found solution of {:?} moves in {:?} us", self.calculated_moves.len(),diff.unwrap());
+        let token = CancelToken::new();
+        let limits = Limits::new().cancel_token(token.clone());
+        let state = self.gameboard.clone();
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let mut solver = create_solver(::SOLVER, &state).expect("unknown solver");
+            // the game may have dropped the receiver.
+            let _ = sender.send(solver.solve_within(&limits));
+        });
+        self.search = Some((token, receiver));
+    }
+    /// Starts playing the moves once the search in the background is over.
+    fn receive_solution(&mut self) {
+        let outcome = match self.search {
+            Some((_, ref receiver)) => match receiver.try_recv() {
+                Ok(outcome) => outcome,
+                Err(mpsc::TryRecvError::Empty) => return,
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    self.search = None;
+                    return;
+                }
+            },
+            None => return,
+        };
+        self.search = None;
+        match outcome {
+            Outcome::Solved(solution) => {
+                println!(
+                    "Found solution of {:?} moves in {:?} us with {}",
+                    solution.moves.len(),
+                    solution.stats.elapsed.as_micros(),
+                    ::SOLVER
+                );
+                self.calculated_moves = solution.moves.into_iter().collect();
+                self.solving = true;
+            }
+            Outcome::Exhausted(_) => println!("No solution could be found"),
+            Outcome::LimitReached(limit, _) => println!("The search stopped : {}", limit),
+            Outcome::Cancelled(_) => println!("Search cancelled"),
+        }
     }
 }
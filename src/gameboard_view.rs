@@ -80,16 +80,15 @@ impl GameboardView {
 
         // Draw section borders.
         let section_edge = Line::new(settings.section_edge_color, settings.section_edge_radius);
-        for i in 0..::SIZE.0 {
-            // Set up coordinates.
-            let x = settings.position[0] + i as f64 / (::SIZE.0 as f64) * settings.size;
-            let y = settings.position[1] + i as f64 / (::SIZE.0 as f64) * settings.size;
-            let x2 = settings.position[0] + settings.size;
-            let y2 = settings.position[1] + settings.size;
-
+        let x2 = settings.position[0] + settings.size;
+        let y2 = settings.position[1] + settings.size;
+        for i in 0..::SIZE.1 {
+            let x = settings.position[0] + i as f64 / (::SIZE.1 as f64) * settings.size;
             let vline = [x, settings.position[1], x, y2];
             section_edge.draw(vline, &c.draw_state, c.transform, g);
-
+        }
+        for i in 0..::SIZE.0 {
+            let y = settings.position[1] + i as f64 / (::SIZE.0 as f64) * settings.size;
             let hline = [settings.position[0], y, x2, y];
             section_edge.draw(hline, &c.draw_state, c.transform, g);
         }
@@ -109,16 +108,17 @@ impl GameboardView {
         g: &mut G,
         settings: &GameboardViewSettings,
     ) {
-        let cell_size = settings.size / (::SIZE.0 as f64);
+        let cell_width = settings.size / (::SIZE.1 as f64);
+        let cell_height = settings.size / (::SIZE.0 as f64);
         let pos = [
-            controller.gameboard.y as f64 * cell_size,
-            controller.gameboard.x as f64 * cell_size,
+            controller.gameboard.y as f64 * cell_width,
+            controller.gameboard.x as f64 * cell_height,
         ];
         let cell_rect = [
             settings.position[0] + pos[0],
             settings.position[1] + pos[1],
-            cell_size,
-            cell_size,
+            cell_width,
+            cell_height,
         ];
         Rectangle::new(settings.selected_cell_background_color)
             .draw(cell_rect, &c.draw_state, c.transform, g);
@@ -141,12 +141,13 @@ impl GameboardView {
     {
         let str = ch.to_string();
         let text_image = Image::new_color(settings.text_color);
-        let cell_size = settings.size / (::SIZE.0 as f64);
-        let font_size = self.settings.size as u32 / ((::SIZE.0 *2) as u32);
+        let cell_width = settings.size / (::SIZE.1 as f64);
+        let cell_height = settings.size / (::SIZE.0 as f64);
+        let font_size = self.settings.size as u32 / ((::SIZE.0.max(::SIZE.1) *2) as u32);
         let pos = [
-            settings.position[0] + i as f64 * cell_size + font_size as f64 / 2.0 -
+            settings.position[0] + i as f64 * cell_width + font_size as f64 / 2.0 -
                 0.5 * (font_size * (str.len() - 1) as u32) as f64 + font_size as f64  ,
-            settings.position[1] + j as f64 * cell_size + font_size as f64,
+            settings.position[1] + j as f64 * cell_height + font_size as f64,
         ];
         for (index, char) in str.chars().enumerate() {
             if let Ok(character) = glyphs.character(font_size, char) {
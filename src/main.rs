@@ -26,7 +26,10 @@ pub use gameboard_view::GameboardViewSettings;
 mod gameboard_controller;
 mod gameboard_view;
 
+/// The number of rows and columns of the board.
 static SIZE: (usize, usize) = (15, 15);
+/// The solver playing the game, one of `taquin::solver::SOLVERS`.
+static SOLVER: &str = "reducer";
 
 fn main() {
     let opengl = OpenGL::V3_2;
@@ -39,7 +42,7 @@ fn main() {
     let mut events = Events::new(EventSettings::new());
     let mut gl = GlGraphics::new(opengl);
 
-    let gameboard = State::new_random(SIZE.0);
+    let gameboard = State::new_random_rect(SIZE.0, SIZE.1, time::precise_time_ns());
     let mut gameboard_controller = GameboardController::new(gameboard);
     let gameboard_view_settings = GameboardViewSettings::new();
     let gameboard_view = GameboardView::new(gameboard_view_settings);
@@ -1,8 +1,17 @@
-//! Astar, contains functions for a star + bfs/dfs.
+//! Astar, contains functions for a star + bfs/dfs, and their solvers.
 
-use std::marker::PhantomData;
-use std::collections::{HashSet, VecDeque, HashMap};
-use std::hash::Hash;
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashSet, VecDeque, HashMap};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::mem;
+use std::sync::atomic::{AtomicBool, AtomicI32, AtomicUsize};
+use std::sync::atomic::Ordering::SeqCst;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Barrier, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use solver::{Limits, Optimality, Outcome, Partial, SearchStats, Solution, Solver, Stop};
 
 /// Represents a node in the graph.
 pub trait DistNode<U: Clone> {
@@ -19,6 +28,11 @@ pub trait DistNode<U: Clone> {
     fn end(&self) -> bool;
     /// Returns the cost from the current state to the given state.
     fn cost_to(&self, target : &Self) -> i32;
+    /// Returns an estimate of the memory used by the state, in bytes.
+    /// Only the state itself is counted by default, not what it points to.
+    fn memory_size(&self) -> usize {
+        size_of_val(self)
+    }
 }
 /// Resolves the history of moves to go from *origin* to *start*
 /// *history* is a hash map which associate a state to a tuple containing
@@ -28,99 +42,1450 @@ pub fn resolve_hist<U: Clone, T: Clone + PartialEq + Eq + Hash>(
     nouv: T,
     history: HashMap<T, (T, U)>,
 ) -> Option<Vec<U>> {
-    let mut start = &nouv;
+    Some(path_to(&origin, &nouv, &history))
+}
+
+/// Returns the moves from *origin* to *node* recorded in the history.
+fn path_to<U: Clone, T: Clone + PartialEq + Eq + Hash>(
+    origin: &T,
+    node: &T,
+    history: &HashMap<T, (T, U)>,
+) -> Vec<U> {
+    let mut start = node;
     let mut hist = vec![];
-    while start != &origin {
-        let &(ref pred, ref mv) = history.get(&start).unwrap();
+    while start != origin {
+        let (pred, mv) = history.get(start).unwrap();
         hist.push(mv.clone());
         start = pred;
     }
     hist.reverse();
-    return Some(hist);
+    hist
+}
+
+/// A search stopped before its end : why, the moves to its most promising
+/// state and the length no solution is shorter than.
+type Interrupted<U> = (Stop, Vec<U>, i32);
+
+/// Turns what a search returned into its outcome, timed from *start*.
+fn outcome<U>(
+    result: Result<Option<Vec<U>>, Interrupted<U>>,
+    optimality: Optimality,
+    stats: SearchStats,
+    start: Instant,
+) -> Outcome<U> {
+    match result {
+        Ok(Some(moves)) => Outcome::Solved(Solution::new(moves, optimality, stats, start)),
+        Ok(None) => Outcome::Exhausted(SearchStats {
+            elapsed: start.elapsed(),
+            ..stats
+        }),
+        Err((stop, moves, lower_bound)) => Outcome::stopped(
+            stop,
+            Partial {
+                moves,
+                lower_bound,
+                stats: SearchStats {
+                    elapsed: start.elapsed(),
+                    ..stats
+                },
+            },
+        ),
+    }
 }
 
-/// Uses bfs to find a way to the final state.
-/// Can be forced to use dfs with the *bfs* arg.
-pub fn bfs<U: Clone, T: DistNode<U> + Hash + Clone + PartialEq + Eq>(
+/// Uses bfs to find the shortest way to the final state.
+/// Every state is kept from the time it is queued, so that it is queued and expanded
+/// only once, see *iddfs* when memory is tight.
+pub fn bfs<U: Clone, T: DistNode<U> + Hash + Clone + PartialEq + Eq>(state: T) -> Option<Vec<U>> {
+    breadth_first(state, &Limits::new(), &mut SearchStats::default()).unwrap_or_default()
+}
+
+/// The breadth first search behind `bfs`, counting its work in *stats*.
+/// When stopped, the most promising state is the last one expanded.
+fn breadth_first<U: Clone, T: DistNode<U> + Hash + Clone + PartialEq + Eq>(
     state: T,
-    bfs: bool,
-) -> Option<Vec<U>> {
+    limits: &Limits,
+    stats: &mut SearchStats,
+) -> Result<Option<Vec<U>>, Interrupted<U>> {
     let mut history: HashMap<T, (T, U)> = HashMap::new();
     let mut visited = HashSet::new();
     let mut f = VecDeque::new();
-    f.push_back(state.clone());
-    while let Some(nouv) = f.pop_front() {
+    let size = state.memory_size();
+    // the states are queued with their depth, and marked visited as they are.
+    visited.insert(state.clone());
+    f.push_back((state.clone(), 0));
+    while let Some((nouv, depth)) = f.pop_front() {
         //println!("hist len : {}", history.len());
         if nouv.end() {
-            return resolve_hist(state, nouv, history);
+            return Ok(resolve_hist(state, nouv, history));
         } else {
-            visited.insert(nouv.clone());
+            if let Some(stop) = limits.check(stats) {
+                // the states closer to the source were all checked.
+                return Err((stop, path_to(&state, &nouv, &history), depth));
+            }
+            stats.expanded += 1;
             for i in nouv.moves() {
                 let mut cop = nouv.clone();
                 cop.modify(&i);
-                if !visited.contains(&cop) {
+                stats.generated += 1;
+                if visited.insert(cop.clone()) {
                     history.insert(cop.clone(), (nouv.clone(), i));
-                    if bfs {
-                        f.push_back(cop);
-                    } else {
-                        f.push_front(cop);
+                    f.push_back((cop, depth + 1));
+                } else {
+                    stats.duplicates += 1;
+                }
+            }
+            // the history holds two states per entry.
+            stats.record(f.len(), (f.len() + visited.len() + 2 * history.len()) * size);
+        }
+    }
+    Ok(None)
+}
+
+/// The breadth first search as a solver.
+#[derive(Debug)]
+pub struct Bfs<T> {
+    source: T,
+}
+
+impl<T> Bfs<T> {
+    /// Creates a breadth first search from the given source.
+    pub fn new(source: T) -> Self {
+        Bfs { source }
+    }
+}
+
+impl<U: Clone, T: DistNode<U> + Hash + Clone + PartialEq + Eq> Solver<U> for Bfs<T> {
+    fn name(&self) -> &'static str {
+        "bfs"
+    }
+    /// Every move costs the same, so the first path found is the shortest.
+    fn solve_within(&mut self, limits: &Limits) -> Outcome<U> {
+        let start = Instant::now();
+        let mut stats = SearchStats::default();
+        let result = breadth_first(self.source.clone(), limits, &mut stats);
+        outcome(result, Optimality::Optimal, stats, start)
+    }
+}
+
+/// The outcome of one depth limited pass of a depth first search.
+enum DfsStep {
+    /// The final state was reached, the path holds the solution.
+    Found,
+    /// Some states were left out because they are deeper than the limit.
+    Cutoff,
+    /// Every state reachable from here without cycles was checked.
+    Exhausted,
+    /// The limits stopped the search.
+    Stopped(Stop),
+}
+
+/// Uses a depth first search to find a way to the final state in at most *depth* moves.
+/// Only the current path is kept in memory : states already on the path are skipped
+/// to avoid cycles, but a state reached by different paths is explored each time.
+/// The way found is not the shortest, see *iddfs*.
+pub fn depth_limited<U: Clone, T: DistNode<U> + Clone + PartialEq>(
+    state: T,
+    depth: usize,
+) -> Option<Vec<U>> {
+    let limits = Limits::new();
+    let mut search = DepthFirst::new(state, &limits);
+    match search.search(depth) {
+        DfsStep::Found => Some(search.hist),
+        _ => None,
+    }
+}
+
+/// Uses iterative deepening depth first search to find the way to the final state
+/// with the fewest moves. Depth limited searches are run with a limit growing by one
+/// move, so only the current path is kept in memory. Returns None when no state is
+/// left out by a search, as no deeper one can reach the final state.
+pub fn iddfs<U: Clone, T: DistNode<U> + Clone + PartialEq>(state: T) -> Option<Vec<U>> {
+    let limits = Limits::new();
+    DepthFirst::new(state, &limits).deepen().unwrap_or_default()
+}
+
+/// The state of a depth first search.
+struct DepthFirst<'a, U, T> {
+    path: Vec<T>,
+    hist: Vec<U>,
+    limits: &'a Limits,
+    stats: SearchStats,
+}
+
+impl<'a, U: Clone, T: DistNode<U> + Clone + PartialEq> DepthFirst<'a, U, T> {
+    fn new(state: T, limits: &'a Limits) -> Self {
+        DepthFirst {
+            path: vec![state],
+            hist: vec![],
+            limits,
+            stats: SearchStats::default(),
+        }
+    }
+
+    /// Runs depth limited searches with increasing limits until a solution is found.
+    /// When stopped, the limit of the pass is a lower bound of the solutions,
+    /// and the most promising state is the one of the current path.
+    fn deepen(&mut self) -> Result<Option<Vec<U>>, Interrupted<U>> {
+        let mut depth = 0;
+        loop {
+            match self.search(depth) {
+                DfsStep::Found => return Ok(Some(self.hist.clone())),
+                DfsStep::Cutoff => depth += 1,
+                DfsStep::Exhausted => return Ok(None),
+                DfsStep::Stopped(stop) => return Err((stop, self.hist.clone(), depth as i32)),
+            }
+        }
+    }
+
+    /// Explores the states reachable from the end of the path in at most *depth* moves.
+    /// States already on the path are skipped to avoid cycles.
+    fn search(&mut self, depth: usize) -> DfsStep {
+        let children = {
+            let node = self.path.last().unwrap();
+            self.stats.record(self.path.len(), self.path.len() * node.memory_size());
+            if node.end() {
+                return DfsStep::Found;
+            }
+            if depth == 0 {
+                return DfsStep::Cutoff;
+            }
+            if let Some(stop) = self.limits.check(&self.stats) {
+                return DfsStep::Stopped(stop);
+            }
+            self.stats.expanded += 1;
+            node.moves()
+                .into_iter()
+                .map(|mv| {
+                    let mut child = node.clone();
+                    child.modify(&mv);
+                    (mv, child)
+                })
+                .collect::<Vec<_>>()
+        };
+        let mut cutoff = false;
+        self.stats.generated += children.len();
+        for (mv, child) in children {
+            if self.path.contains(&child) {
+                self.stats.duplicates += 1;
+                continue;
+            }
+            self.path.push(child);
+            self.hist.push(mv);
+            match self.search(depth - 1) {
+                DfsStep::Found => return DfsStep::Found,
+                DfsStep::Cutoff => cutoff = true,
+                DfsStep::Exhausted => {}
+                DfsStep::Stopped(stop) => return DfsStep::Stopped(stop),
+            }
+            self.path.pop();
+            self.hist.pop();
+        }
+        if cutoff {
+            DfsStep::Cutoff
+        } else {
+            DfsStep::Exhausted
+        }
+    }
+}
+
+/// Iterative deepening depth first search as a solver.
+#[derive(Debug)]
+pub struct Iddfs<T> {
+    source: T,
+}
+
+impl<T> Iddfs<T> {
+    /// Creates an iterative deepening depth first search from the given source.
+    pub fn new(source: T) -> Self {
+        Iddfs { source }
+    }
+}
+
+impl<U: Clone, T: DistNode<U> + Clone + PartialEq> Solver<U> for Iddfs<T> {
+    fn name(&self) -> &'static str {
+        "iddfs"
+    }
+    /// The solution has the fewest moves, which is the shortest when every move costs the same.
+    fn solve_within(&mut self, limits: &Limits) -> Outcome<U> {
+        let start = Instant::now();
+        let mut search = DepthFirst::new(self.source.clone(), limits);
+        let result = search.deepen();
+        outcome(result, Optimality::Optimal, search.stats, start)
+    }
+}
+
+/// A node whose moves can be undone, so that the graph can also be searched
+/// backwards from the final state.
+pub trait ReversibleNode<U: Clone>: DistNode<U> {
+    /// Returns the move undoing the given one, applying both gives back the state.
+    fn reverse_move(&self, mv: &U) -> U;
+    /// Returns the final state, the only one for which *end* is true.
+    fn final_node(&self) -> Self;
+}
+
+/// The states reached by one side of a bidirectional search, with their depth
+/// and the state and move linking them to the side's origin.
+type Reached<U, T> = HashMap<T, (i32, Option<(T, U)>)>;
+
+/// One side of a bidirectional search.
+struct Side<U, T> {
+    reached: Reached<U, T>,
+    frontier: Vec<T>,
+    depth: i32,
+}
+
+impl<U: Clone, T: ReversibleNode<U> + Hash + Clone + PartialEq + Eq> Side<U, T> {
+    fn new(origin: T) -> Self {
+        let mut reached = HashMap::new();
+        reached.insert(origin.clone(), (0, None));
+        Side {
+            reached,
+            frontier: vec![origin],
+            depth: 0,
+        }
+    }
+    /// Returns the moves stored from the node back to the origin of the side.
+    fn links(&self, node: &T) -> Vec<U> {
+        let mut moves = vec![];
+        let mut current = node;
+        while let Some(&(_, Some((ref previous, ref mv)))) = self.reached.get(current) {
+            moves.push(mv.clone());
+            current = previous;
+        }
+        moves
+    }
+    /// Returns the moves from the origin of this side to the one of the other side,
+    /// through a state both sides reached.
+    fn join(&self, other: &Self, middle: &T) -> Vec<U> {
+        let mut moves = self.links(middle);
+        moves.reverse();
+        moves.extend(other.links(middle));
+        moves
+    }
+}
+
+/// Uses a breadth first search from both ends to find the shortest way to the final state.
+/// The frontier with the fewest states is expanded one depth at a time, until
+/// the two searches meet. Every move must cost the same.
+pub fn bidirectional_bfs<U: Clone, T: ReversibleNode<U> + Hash + Clone + PartialEq + Eq>(
+    state: T,
+) -> Option<Vec<U>> {
+    bidirectional(state, &Limits::new(), &mut SearchStats::default()).unwrap_or_default()
+}
+
+/// The search behind `bidirectional_bfs`, counting its work in *stats*.
+/// When stopped, the most promising state is the deepest reached from the start,
+/// unless the sides already met in the unfinished layer : the path through the
+/// meeting state is then given, though it may not be the shortest.
+fn bidirectional<U: Clone, T: ReversibleNode<U> + Hash + Clone + PartialEq + Eq>(
+    state: T,
+    limits: &Limits,
+    stats: &mut SearchStats,
+) -> Result<Option<Vec<U>>, Interrupted<U>> {
+    if state.end() {
+        return Ok(Some(vec![]));
+    }
+    let size = state.memory_size();
+    let mut forward = Side::new(state.clone());
+    let mut backward = Side::new(state.final_node());
+    while !forward.frontier.is_empty() && !backward.frontier.is_empty() {
+        let expands_forward = forward.frontier.len() <= backward.frontier.len();
+        let (side, other) = if expands_forward {
+            (&mut forward, &backward)
+        } else {
+            (&mut backward, &forward)
+        };
+        // the shortest path through a state reached by both sides, in this layer.
+        let mut meeting: Option<(i32, T)> = None;
+        let mut stopped = None;
+        let mut next = vec![];
+        for node in mem::take(&mut side.frontier) {
+            if let Some(stop) = limits.check(stats) {
+                stopped = Some((stop, node));
+                break;
+            }
+            stats.expanded += 1;
+            for mv in node.moves() {
+                let mut child = node.clone();
+                child.modify(&mv);
+                stats.generated += 1;
+                if side.reached.contains_key(&child) {
+                    stats.duplicates += 1;
+                    continue;
+                }
+                if let Some(&(depth, _)) = other.reached.get(&child) {
+                    let length = side.depth + 1 + depth;
+                    if meeting.as_ref().is_none_or(|&(best, _)| length < best) {
+                        meeting = Some((length, child.clone()));
                     }
                 }
+                // the backward side stores the moves taken towards the final state.
+                let link = if expands_forward {
+                    mv
+                } else {
+                    child.reverse_move(&mv)
+                };
+                side.reached.insert(child.clone(), (side.depth + 1, Some((node.clone(), link))));
+                next.push(child);
+            }
+            let open = next.len() + other.frontier.len();
+            // each reached state is stored with the one it comes from.
+            let stored = 2 * (side.reached.len() + other.reached.len()) + open;
+            stats.record(open, stored * size);
+        }
+        if let Some((stop, node)) = stopped {
+            let moves = match meeting {
+                Some((_, middle)) => forward.join(&backward, &middle),
+                None => {
+                    let deepest = if expands_forward {
+                        node
+                    } else {
+                        forward.frontier[0].clone()
+                    };
+                    let mut moves = forward.links(&deepest);
+                    moves.reverse();
+                    moves
+                }
+            };
+            return Err((stop, moves, forward.depth + backward.depth));
+        }
+        side.frontier = next;
+        side.depth += 1;
+        if let Some((_, middle)) = meeting {
+            return Ok(Some(forward.join(&backward, &middle)));
+        }
+    }
+    Ok(None)
+}
+
+/// The bidirectional breadth first search as a solver.
+#[derive(Debug)]
+pub struct Bidirectional<T> {
+    source: T,
+}
+
+impl<T> Bidirectional<T> {
+    /// Creates a bidirectional breadth first search from the given source.
+    pub fn new(source: T) -> Self {
+        Bidirectional { source }
+    }
+}
+
+impl<U: Clone, T: ReversibleNode<U> + Hash + Clone + PartialEq + Eq> Solver<U> for Bidirectional<T> {
+    fn name(&self) -> &'static str {
+        "bidirectional"
+    }
+    /// The searches meet on a shortest path, as every move costs the same.
+    fn solve_within(&mut self, limits: &Limits) -> Outcome<U> {
+        let start = Instant::now();
+        let mut stats = SearchStats::default();
+        let result = bidirectional(self.source.clone(), limits, &mut stats);
+        outcome(result, Optimality::Optimal, stats, start)
+    }
+}
+
+/// The outcome of one bounded depth first pass of ida star.
+enum IdaStep {
+    /// The final state was reached, the path holds the solution.
+    Found,
+    /// The bound was exceeded, contains the smallest f cost above the bound.
+    Exceeded(i32),
+    /// No state could be reached from here.
+    Exhausted,
+    /// The limits stopped the search.
+    Stopped(Stop),
+}
+
+/// Uses iterative deepening a star to find the shortest way to the final state.
+/// Only the current path is kept in memory, the search is restarted with a
+/// higher bound on the f cost each time it fails.
+/// The result is optimal as long as *dist_from_end* never overestimates.
+pub fn ida_star<U: Clone, T: DistNode<U> + Clone + PartialEq>(state: T) -> Option<Vec<U>> {
+    let limits = Limits::new();
+    IdaSearch::new(state, &limits).run().unwrap_or_default()
+}
+
+/// The state of an iterative deepening a star search.
+struct IdaSearch<'a, U, T> {
+    path: Vec<T>,
+    hist: Vec<U>,
+    limits: &'a Limits,
+    stats: SearchStats,
+    /// The lowest estimate seen, and the moves to its state.
+    closest: (i32, Vec<U>),
+}
+
+impl<'a, U: Clone, T: DistNode<U> + Clone + PartialEq> IdaSearch<'a, U, T> {
+    fn new(state: T, limits: &'a Limits) -> Self {
+        IdaSearch {
+            path: vec![state],
+            hist: vec![],
+            limits,
+            stats: SearchStats::default(),
+            closest: (i32::MAX, vec![]),
+        }
+    }
+
+    /// Runs passes with increasing bounds until a solution is found.
+    /// When stopped, the bound of the pass is a lower bound of the solutions.
+    fn run(&mut self) -> Result<Option<Vec<U>>, Interrupted<U>> {
+        let mut bound = self.path[0].dist_from_end();
+        self.stats.heuristic_evals += 1;
+        loop {
+            match self.search(0, bound) {
+                IdaStep::Found => return Ok(Some(self.hist.clone())),
+                IdaStep::Exceeded(next) => bound = next,
+                IdaStep::Exhausted => return Ok(None),
+                IdaStep::Stopped(stop) => return Err((stop, self.closest.1.clone(), bound)),
+            }
+        }
+    }
+
+    /// Explores the states reachable from the end of the path without exceeding *bound*.
+    /// States already on the path are skipped to avoid cycles.
+    fn search(&mut self, cost: i32, bound: i32) -> IdaStep {
+        let children = {
+            let node = self.path.last().unwrap();
+            let h = node.dist_from_end();
+            let f = cost + h;
+            self.stats.heuristic_evals += 1;
+            self.stats.record(self.path.len(), self.path.len() * node.memory_size());
+            if h < self.closest.0 {
+                self.closest = (h, self.hist.clone());
             }
+            if f > bound {
+                return IdaStep::Exceeded(f);
+            }
+            if node.end() {
+                return IdaStep::Found;
+            }
+            if let Some(stop) = self.limits.check(&self.stats) {
+                return IdaStep::Stopped(stop);
+            }
+            self.stats.expanded += 1;
+            node.moves()
+                .into_iter()
+                .map(|mv| {
+                    let mut child = node.clone();
+                    child.modify(&mv);
+                    let step = node.cost_to(&child);
+                    (mv, child, step)
+                })
+                .collect::<Vec<_>>()
+        };
+        let mut next = None;
+        self.stats.generated += children.len();
+        for (mv, child, step) in children {
+            if self.path.contains(&child) {
+                self.stats.duplicates += 1;
+                continue;
+            }
+            self.path.push(child);
+            self.hist.push(mv);
+            match self.search(cost + step, bound) {
+                IdaStep::Found => return IdaStep::Found,
+                IdaStep::Exceeded(f) => next = Some(next.map_or(f, |n: i32| n.min(f))),
+                IdaStep::Exhausted => {}
+                IdaStep::Stopped(stop) => return IdaStep::Stopped(stop),
+            }
+            self.path.pop();
+            self.hist.pop();
         }
+        next.map_or(IdaStep::Exhausted, IdaStep::Exceeded)
+    }
+}
+
+/// Iterative deepening a star as a solver.
+#[derive(Debug)]
+pub struct IdaStar<T> {
+    source: T,
+}
+
+impl<T> IdaStar<T> {
+    /// Creates an iterative deepening a star from the given source.
+    pub fn new(source: T) -> Self {
+        IdaStar { source }
+    }
+}
+
+impl<U: Clone, T: DistNode<U> + Clone + PartialEq> Solver<U> for IdaStar<T> {
+    fn name(&self) -> &'static str {
+        "idastar"
+    }
+    fn solve_within(&mut self, limits: &Limits) -> Outcome<U> {
+        let start = Instant::now();
+        let mut search = IdaSearch::new(self.source.clone(), limits);
+        let result = search.run();
+        outcome(result, Optimality::Optimal, search.stats, start)
+    }
+}
+
+/// An entry of the open list.
+/// Entries are ordered so that the lowest f cost comes out of the heap first,
+/// ties are broken in favor of the lowest estimated distance to the end, then of the
+/// highest cost from the start, so that the order is total.
+#[derive(Debug)]
+struct OpenNode<T> {
+    f: f64,
+    g: i32,
+    h: i32,
+    node: T,
+}
+
+impl<T> Ord for OpenNode<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .f
+            .total_cmp(&self.f)
+            .then_with(|| other.h.cmp(&self.h))
+            .then_with(|| self.g.cmp(&other.g))
+    }
+}
+
+impl<T> PartialOrd for OpenNode<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T> PartialEq for OpenNode<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<T> Eq for OpenNode<T> {}
+
+/// A better solution found by anytime astar.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Improvement<U> {
+    /// The moves of the solution.
+    pub moves: Vec<U>,
+    /// No solution costs less than this.
+    pub lower_bound: i32,
+    /// The solution costs at most this many times the optimal one.
+    pub bound: f64,
+    /// The time since the search started.
+    pub elapsed: Duration,
+}
+
+/// Returns how many times the lower bound the cost is at most.
+fn ratio(cost: i32, lower_bound: i32) -> f64 {
+    if cost <= lower_bound {
+        1.0
+    } else if lower_bound <= 0 {
+        f64::INFINITY
+    } else {
+        f64::from(cost) / f64::from(lower_bound)
     }
-    return None;
 }
+
 #[derive(Debug)]
 /// THe astar struct containing the elements used during the algorithm.
 pub struct AStar<U: Clone, T: DistNode<U> + Hash + Clone + PartialEq + Eq> {
-    source : T,
-    f_costs : HashMap<T, i32>,
-    ongoing : HashSet<T>,
-    phantom: PhantomData<U>,
+    source: T,
+    /// The factor of the heuristic in the f cost.
+    weight: f64,
+    open: BinaryHeap<OpenNode<T>>,
+    lowest_to: HashMap<T, i32>,
+    best_previous: HashMap<T, (T, U)>,
 }
-impl<U: Clone, T: DistNode<U> + Hash + Clone + PartialEq + Eq> AStar<U,T>{
+impl<U: Clone, T: DistNode<U> + Hash + Clone + PartialEq + Eq> AStar<U, T> {
     /// Creates a new astar from the given source.
-    pub fn new(source : T) -> Self {
+    pub fn new(source: T) -> Self {
         AStar {
-            source: source,
-            f_costs : HashMap::new(),
-            ongoing : HashSet::new(),
-            phantom : PhantomData,
+            source,
+            weight: 1.0,
+            open: BinaryHeap::new(),
+            lowest_to: HashMap::new(),
+            best_previous: HashMap::new(),
         }
     }
-    /// Returns the best node, maximizing f_costs
-    pub fn best_node(&self) -> T{
-        self.ongoing.iter().max_by_key(|k|self.f_costs.get(k)).unwrap().clone()
+    /// Multiplies the heuristic by the weight in the f cost : f = g + w * h.
+    /// The search goes faster towards the end, and its solutions cost at most
+    /// w times the optimal one. The weight can not be lower than 1.
+    pub fn weight(mut self, weight: f64) -> Self {
+        assert!(weight >= 1.0, "the weight {} is lower than 1", weight);
+        self.weight = weight;
+        self
+    }
+    /// Removes the best node from the open list, minimizing f_costs.
+    /// Returns the node with the cost of the path found to it.
+    /// Entries left behind when a cheaper path to their node was found are skipped.
+    pub fn best_node(&mut self) -> Option<(T, i32)> {
+        self.best_entry().map(|entry| (entry.node, entry.g))
     }
 
-    /// Uses astar to find the shortest way to the final state.
-    pub fn solve(&mut self) ->Option<Vec<U>> {
+    /// Removes the best entry from the open list, skipping the ones left behind.
+    fn best_entry(&mut self) -> Option<OpenNode<T>> {
+        while let Some(entry) = self.open.pop() {
+            if self.lowest_to.get(&entry.node) == Some(&entry.g) {
+                return Some(entry);
+            }
+        }
+        None
+    }
+
+    /// Adds a node to the open list, reached with the given cost.
+    fn push(&mut self, node: T, g: i32, stats: &mut SearchStats) {
+        let h = node.dist_from_end();
+        stats.heuristic_evals += 1;
+        self.open.push(OpenNode {
+            f: f64::from(g) + self.weight * f64::from(h),
+            g,
+            h,
+            node,
+        });
+    }
+
+    /// Empties the lists and puts the source in the open list.
+    fn restart(&mut self, stats: &mut SearchStats) {
+        self.open.clear();
+        self.lowest_to.clear();
+        self.best_previous.clear();
+        self.lowest_to.insert(self.source.clone(), 0);
+        self.push(self.source.clone(), 0, stats);
+    }
+
+    /// Adds the neighbours of the node reached with the given cost to the open
+    /// list, unless a path as cheap to them is known.
+    fn expand(&mut self, node: &T, cost: i32, stats: &mut SearchStats) {
+        stats.expanded += 1;
+        for i in node.moves() {
+            let mut n = node.clone();
+            n.modify(&i);
+            stats.generated += 1;
+            let new_cost = cost + node.cost_to(&n);
+            let improves = match self.lowest_to.get(&n) {
+                Some(&known) => new_cost < known,
+                None => true,
+            };
+            if improves {
+                self.lowest_to.insert(n.clone(), new_cost);
+                self.best_previous.insert(n.clone(), (node.clone(), i));
+                self.push(n, new_cost, stats);
+            } else {
+                stats.duplicates += 1;
+            }
+        }
+        let stored = self.open.len() + self.lowest_to.len() + 2 * self.best_previous.len();
+        stats.record(self.open.len(), stored * node.memory_size());
+    }
+
+    /// Returns a lower bound of the cost of the solutions, knowing one of the given cost.
+    /// A cheaper solution goes through an open node, and the heuristic never overestimates.
+    fn lower_bound(&self, cost: i32) -> i32 {
+        self.open.iter().map(|entry| entry.g + entry.h).fold(cost, i32::min)
+    }
+
+    /// The search behind `solve_within`, counting its work in *stats*.
+    /// When stopped, the most promising state is the one with the lowest estimate
+    /// expanded, and the f cost of the last one over the weight is a lower bound of the solutions.
+    fn search(
+        &mut self,
+        limits: &Limits,
+        stats: &mut SearchStats,
+    ) -> Result<Option<Vec<U>>, Interrupted<U>> {
         let source = self.source.clone();
-        self.ongoing.insert(source.clone());
-        let mut lowest_to = HashMap::new();
-        lowest_to.insert(source.clone(), 0);
-        let mut best_previous = HashMap::new();
-        while !self.ongoing.is_empty() {
-            let best_node = self.best_node();
+        self.restart(stats);
+        let mut closest: Option<(i32, T)> = None;
+        while let Some(entry) = self.best_entry() {
+            let OpenNode {
+                f,
+                g: cost,
+                h,
+                node: best_node,
+            } = entry;
             if best_node.end() {
-                return resolve_hist(source,best_node,best_previous);
-            }
-            self.ongoing.remove(&best_node);
-            for i in best_node.moves() {
-                let mut n = best_node.clone();
-                n.modify(&i);
-                let new_cost = lowest_to.get(&best_node).unwrap() + best_node.cost_to(&n);
-                if !lowest_to.contains_key(&n) || new_cost < *lowest_to.get(&n).unwrap(){
-                    lowest_to.insert(n.clone(), new_cost);
-                    best_previous.insert(n.clone(), (best_node.clone(), i));
-                    self.f_costs.insert(n.clone(), new_cost + best_node.dist_from_end());
-                    self.ongoing.insert(n.clone());
+                let history = mem::take(&mut self.best_previous);
+                return Ok(resolve_hist(source, best_node, history));
+            }
+            if closest.as_ref().is_none_or(|&(lowest, _)| h < lowest) {
+                closest = Some((h, best_node.clone()));
+            }
+            if let Some(stop) = limits.check(stats) {
+                let moves = match closest {
+                    Some((_, ref node)) => path_to(&source, node, &self.best_previous),
+                    None => vec![],
+                };
+                return Err((stop, moves, (f / self.weight).ceil() as i32));
+            }
+            self.expand(&best_node, cost, stats);
+        }
+        Ok(None)
+    }
+
+    /// Uses anytime astar : the first solution of the weighted search is reported,
+    /// then the search goes on to find cheaper ones, each of them being reported
+    /// with a bound of how far it may be from the optimal one.
+    /// The search ends when no cheaper solution can exist or when the limits are reached,
+    /// usually a deadline, and returns the best solution found.
+    pub fn anytime<F>(&mut self, limits: &Limits, mut report: F) -> Outcome<U>
+    where
+        F: FnMut(&Improvement<U>),
+    {
+        let start = Instant::now();
+        let mut stats = SearchStats::default();
+        let source = self.source.clone();
+        self.restart(&mut stats);
+        let mut best: Option<(i32, Vec<U>)> = None;
+        let mut stopped = None;
+        while let Some(entry) = self.best_entry() {
+            if let Some((cost, _)) = best {
+                if entry.g + entry.h >= cost {
+                    continue;
                 }
             }
+            if entry.node.end() {
+                let moves = path_to(&source, &entry.node, &self.best_previous);
+                let lower_bound = self.lower_bound(entry.g);
+                report(&Improvement {
+                    moves: moves.clone(),
+                    lower_bound,
+                    bound: ratio(entry.g, lower_bound),
+                    elapsed: start.elapsed(),
+                });
+                best = Some((entry.g, moves));
+                continue;
+            }
+            if let Some(stop) = limits.check(&stats) {
+                stopped = Some((stop, entry.f));
+                break;
+            }
+            self.expand(&entry.node, entry.g, &mut stats);
+        }
+        match (best, stopped) {
+            (Some((cost, moves)), _) => {
+                let optimality = match ratio(cost, self.lower_bound(cost)) {
+                    bound if bound > 1.0 => Optimality::Bounded(bound),
+                    _ => Optimality::Optimal,
+                };
+                Outcome::Solved(Solution::new(moves, optimality, stats, start))
+            }
+            (None, Some((stop, f))) => {
+                let lower_bound = (f / self.weight).ceil() as i32;
+                outcome(Err((stop, vec![], lower_bound)), Optimality::Optimal, stats, start)
+            }
+            (None, None) => outcome(Ok(None), Optimality::Optimal, stats, start),
+        }
+    }
+}
+
+impl<U: Clone, T: DistNode<U> + Hash + Clone + PartialEq + Eq> Solver<U> for AStar<U, T> {
+    fn name(&self) -> &'static str {
+        "astar"
+    }
+    /// Uses astar to find the shortest way to the final state.
+    /// The result is optimal as long as *dist_from_end* never overestimates,
+    /// or at most the weight times the optimal cost.
+    fn solve_within(&mut self, limits: &Limits) -> Outcome<U> {
+        let start = Instant::now();
+        let mut stats = SearchStats::default();
+        let result = self.search(limits, &mut stats);
+        let optimality = if self.weight > 1.0 {
+            Optimality::Bounded(self.weight)
+        } else {
+            Optimality::Optimal
+        };
+        outcome(result, optimality, stats, start)
+    }
+}
+
+/// A state sent to the thread owning it : the cost of the path found to it,
+/// and the state and move it was reached from, none for the source.
+type Message<U, T> = (T, i32, Option<(T, U)>);
+
+/// What the threads send each other : a state, or None to wake up a thread waiting
+/// for one once the search is over.
+type Letter<U, T> = Option<Message<U, T>>;
+
+/// Wakes up the threads waiting for a state, so that they see the search is over.
+fn wake<U, T>(outboxes: &[Sender<Letter<U, T>>]) {
+    for outbox in outboxes {
+        outbox.send(None).expect("the threads receive until every one stops sending");
+    }
+}
+
+/// Returns the thread owning the state among the given number of threads.
+/// The hasher has fixed keys, so a state always has the same owner.
+fn owner<T: Hash>(node: &T, threads: usize) -> usize {
+    let mut hasher = DefaultHasher::new();
+    node.hash(&mut hasher);
+    (hasher.finish() % threads as u64) as usize
+}
+
+/// What the threads of the parallel astar share.
+#[derive(Debug)]
+struct Shared<'a> {
+    limits: &'a Limits,
+    /// The cost of the cheapest solution found, i32::MAX until one is found.
+    incumbent: AtomicI32,
+    /// The messages not yet handled plus the threads busy expanding states :
+    /// once it reaches 0 no thread has anything left to do, and it stays there.
+    work: AtomicUsize,
+    /// The states expanded by all the threads.
+    expanded: AtomicUsize,
+    /// The memory used by the states of each thread.
+    memory: Vec<AtomicUsize>,
+    /// Why the search stopped, set by the first thread reaching a limit.
+    stop: Mutex<Option<Stop>>,
+    stopped: AtomicBool,
+}
+
+impl<'a> Shared<'a> {
+    /// Returns why the threads must stop, counting the work of all of them.
+    fn check(&self) -> Option<Stop> {
+        let stats = SearchStats {
+            expanded: self.expanded.load(SeqCst),
+            peak_memory: self.memory.iter().map(|memory| memory.load(SeqCst)).sum(),
+            ..SearchStats::default()
+        };
+        self.limits.check(&stats)
+    }
+    /// Asks every thread to stop, keeping the first reason given.
+    fn stop(&self, stop: Stop) {
+        self.stop.lock().unwrap().get_or_insert(stop);
+        self.stopped.store(true, SeqCst);
+    }
+}
+
+/// A thread of the parallel astar, with the states it owns.
+#[derive(Debug)]
+struct Worker<U, T> {
+    id: usize,
+    open: BinaryHeap<OpenNode<T>>,
+    /// The cost of the cheapest path found to each state owned, and where it comes from.
+    reached: Reached<U, T>,
+    /// The cheapest final state expanded by this thread.
+    found: Option<T>,
+    /// The state with the lowest estimate expanded by this thread.
+    closest: Option<(i32, T)>,
+    stats: SearchStats,
+}
+
+impl<U: Clone, T: DistNode<U> + Hash + Clone + PartialEq + Eq> Worker<U, T> {
+    fn new(id: usize) -> Self {
+        Worker {
+            id,
+            open: BinaryHeap::new(),
+            reached: HashMap::new(),
+            found: None,
+            closest: None,
+            stats: SearchStats::default(),
+        }
+    }
+
+    /// Adds a state to the open list, unless a path as cheap to it is known.
+    fn receive(&mut self, (node, g, previous): Message<U, T>) {
+        if self.reached.get(&node).is_some_and(|&(known, _)| known <= g) {
+            self.stats.duplicates += 1;
+            return;
+        }
+        let h = node.dist_from_end();
+        self.stats.heuristic_evals += 1;
+        self.reached.insert(node.clone(), (g, previous));
+        self.open.push(OpenNode {
+            f: f64::from(g + h),
+            g,
+            h,
+            node,
+        });
+    }
+
+    /// Removes the best entry from the open list, skipping the ones left behind
+    /// and the ones which can not lead to a solution cheaper than the incumbent.
+    fn best_entry(&mut self, incumbent: i32) -> Option<OpenNode<T>> {
+        while let Some(entry) = self.open.pop() {
+            if entry.g + entry.h < incumbent && self.reached.get(&entry.node).map(|r| r.0) == Some(entry.g) {
+                return Some(entry);
+            }
         }
         None
     }
 
+    /// Sends the neighbours of the node reached with the given cost to their owners.
+    fn expand(&mut self, node: &T, cost: i32, shared: &Shared, outboxes: &[Sender<Letter<U, T>>]) {
+        self.stats.expanded += 1;
+        shared.expanded.fetch_add(1, SeqCst);
+        for mv in node.moves() {
+            let mut next = node.clone();
+            next.modify(&mv);
+            self.stats.generated += 1;
+            let g = cost + node.cost_to(&next);
+            let to = owner(&next, outboxes.len());
+            let message = (next, g, Some((node.clone(), mv)));
+            if to == self.id {
+                self.receive(message);
+            } else {
+                shared.work.fetch_add(1, SeqCst);
+                outboxes[to].send(Some(message)).expect("the threads receive until every one stops sending");
+            }
+        }
+        let memory = (self.open.len() + 3 * self.reached.len()) * node.memory_size();
+        shared.memory[self.id].store(memory, SeqCst);
+        self.stats.record(self.open.len(), memory);
+    }
+
+    /// Waits for a message while the thread has nothing to expand.
+    /// Returns false when the search is over : no thread has anything left to do,
+    /// or one of them reached a limit. The last thread to run out of work wakes
+    /// the others up.
+    fn wait(
+        &mut self,
+        shared: &Shared,
+        inbox: &Receiver<Letter<U, T>>,
+        outboxes: &[Sender<Letter<U, T>>],
+    ) -> bool {
+        if shared.work.fetch_sub(1, SeqCst) == 1 {
+            wake(outboxes);
+        }
+        while shared.work.load(SeqCst) > 0 && !shared.stopped.load(SeqCst) {
+            let message = inbox.recv().expect("the threads send until every one stops receiving");
+            if let Some(message) = message {
+                // busy again before the message is counted as handled.
+                shared.work.fetch_add(1, SeqCst);
+                self.receive(message);
+                shared.work.fetch_sub(1, SeqCst);
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Expands the states owned until the search is over, then adds the messages
+    /// left once every thread stopped, so that the lower bound counts them.
+    fn run(
+        &mut self,
+        shared: &Shared,
+        barrier: &Barrier,
+        inbox: &Receiver<Letter<U, T>>,
+        outboxes: &[Sender<Letter<U, T>>],
+    ) {
+        loop {
+            while let Ok(letter) = inbox.try_recv() {
+                if let Some(message) = letter {
+                    self.receive(message);
+                    shared.work.fetch_sub(1, SeqCst);
+                }
+            }
+            if shared.stopped.load(SeqCst) {
+                break;
+            }
+            let entry = match self.best_entry(shared.incumbent.load(SeqCst)) {
+                Some(entry) => entry,
+                None if self.wait(shared, inbox, outboxes) => continue,
+                None => break,
+            };
+            if entry.node.end() {
+                shared.incumbent.fetch_min(entry.g, SeqCst);
+                self.found = Some(entry.node);
+                continue;
+            }
+            if self.closest.as_ref().is_none_or(|&(lowest, _)| entry.h < lowest) {
+                self.closest = Some((entry.h, entry.node.clone()));
+            }
+            if let Some(stop) = shared.check() {
+                shared.stop(stop);
+                wake(outboxes);
+                // the entry is still to expand.
+                self.open.push(entry);
+                break;
+            }
+            self.expand(&entry.node, entry.g, shared, outboxes);
+        }
+        barrier.wait();
+        for message in inbox.try_iter().flatten() {
+            self.receive(message);
+        }
+    }
+
+    /// Returns the lowest cost a solution through the open list can have.
+    fn lower_bound(&self) -> i32 {
+        self.open
+            .iter()
+            .filter(|entry| self.reached.get(&entry.node).map(|r| r.0) == Some(entry.g))
+            .map(|entry| entry.g + entry.h)
+            .fold(i32::MAX, i32::min)
+    }
+}
+
+/// Returns the moves to the node recorded by the threads owning it and its predecessors.
+fn path_through<U: Clone, T: Hash + Clone + PartialEq + Eq>(workers: &[Worker<U, T>], node: &T) -> Vec<U> {
+    let mut node = node.clone();
+    let mut moves = vec![];
+    while let Some((previous, mv)) = workers[owner(&node, workers.len())].reached[&node].1.clone() {
+        moves.push(mv);
+        node = previous;
+    }
+    moves.reverse();
+    moves
 }
 
+/// Returns the cheapest final state found by the threads with its cost, at most the incumbent.
+/// A message may have lowered the cost of a final state after it was found :
+/// the path recorded to it is then cheaper and still valid.
+fn cheapest_found<U, T: Hash + Eq>(workers: &[Worker<U, T>], incumbent: i32) -> Option<(i32, &T)> {
+    workers
+        .iter()
+        .filter_map(|worker| worker.found.as_ref())
+        .map(|node| (workers[owner(node, workers.len())].reached[node].0, node))
+        .filter(|&(cost, _)| cost <= incumbent)
+        .min_by_key(|&(cost, _)| cost)
+}
+
+#[derive(Debug)]
+/// The parallel astar struct : hash distributed astar.
+/// Each state is owned by one thread, chosen by the hash of the state, which keeps
+/// its cost and the open list of its states. The neighbours of an expanded state
+/// are sent to their owners, so the threads share no list.
+pub struct ParallelAStar<T> {
+    source: T,
+    threads: usize,
+}
+
+impl<T> ParallelAStar<T> {
+    /// Creates a parallel astar from the source, with a thread per available core.
+    pub fn new(source: T) -> Self {
+        ParallelAStar {
+            source,
+            threads: thread::available_parallelism().map_or(1, |threads| threads.get()),
+        }
+    }
+    /// Sets the number of threads, at least 1.
+    pub fn threads(mut self, threads: usize) -> Self {
+        assert!(threads >= 1, "the search needs at least 1 thread");
+        self.threads = threads;
+        self
+    }
+}
+
+impl<U, T> Solver<U> for ParallelAStar<T>
+where
+    U: Clone + Send,
+    T: DistNode<U> + Hash + Clone + PartialEq + Eq + Send,
+{
+    fn name(&self) -> &'static str {
+        "parallel-astar"
+    }
+    /// Uses hash distributed astar to find the shortest way to the final state.
+    /// The threads expand states in parallel, so the first solution found may not be
+    /// the shortest : the search goes on until no open state can lead to a cheaper one,
+    /// and the solutions have the length of the ones of astar.
+    /// When stopped after finding a solution, it is returned with the bound known.
+    fn solve_within(&mut self, limits: &Limits) -> Outcome<U> {
+        let start = Instant::now();
+        let threads = self.threads;
+        let shared = Shared {
+            limits,
+            incumbent: AtomicI32::new(i32::MAX),
+            work: AtomicUsize::new(threads),
+            expanded: AtomicUsize::new(0),
+            memory: (0..threads).map(|_| AtomicUsize::new(0)).collect(),
+            stop: Mutex::new(None),
+            stopped: AtomicBool::new(false),
+        };
+        let barrier = Barrier::new(threads);
+        let (outboxes, inboxes): (Vec<_>, Vec<_>) = (0..threads).map(|_| mpsc::channel()).unzip();
+        let mut workers: Vec<Worker<U, T>> = (0..threads).map(Worker::new).collect();
+        workers[owner(&self.source, threads)].receive((self.source.clone(), 0, None));
+        thread::scope(|scope| {
+            let handles = workers
+                .iter_mut()
+                .zip(inboxes)
+                .map(|(worker, inbox)| {
+                    let (shared, barrier, outboxes) = (&shared, &barrier, outboxes.clone());
+                    scope.spawn(move || worker.run(shared, barrier, &inbox, &outboxes))
+                })
+                .collect::<Vec<_>>();
+            for handle in handles {
+                handle.join().expect("a thread of the search panicked");
+            }
+        });
+        let mut stats = SearchStats::default();
+        for worker in workers.iter() {
+            stats.add(&worker.stats);
+        }
+        // the threads hold their states at the same time.
+        stats.peak_open = workers.iter().map(|worker| worker.stats.peak_open).sum();
+        stats.peak_memory = workers.iter().map(|worker| worker.stats.peak_memory).sum();
+        let incumbent = shared.incumbent.load(SeqCst);
+        let found = cheapest_found(&workers, incumbent);
+        let cost = found.map_or(incumbent, |(cost, _)| cost);
+        let lower_bound = workers.iter().map(Worker::lower_bound).fold(cost, i32::min);
+        let stop = *shared.stop.lock().unwrap();
+        match (found, stop) {
+            (Some((cost, node)), _) => {
+                let optimality = match ratio(cost, lower_bound) {
+                    bound if bound > 1.0 => Optimality::Bounded(bound),
+                    _ => Optimality::Optimal,
+                };
+                Outcome::Solved(Solution::new(path_through(&workers, node), optimality, stats, start))
+            }
+            (None, Some(stop)) => {
+                let moves = workers
+                    .iter()
+                    .filter_map(|worker| worker.closest.as_ref())
+                    .min_by_key(|&&(h, _)| h)
+                    .map_or(vec![], |(_, node)| path_through(&workers, node));
+                outcome(Err((stop, moves, lower_bound)), Optimality::Optimal, stats, start)
+            }
+            (None, None) => outcome(Ok(None), Optimality::Optimal, stats, start),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use direction::Direction;
+    use moves::{verify_solution, MoveSeq};
+    use packed::PackedState;
+    use solver::Limit;
+    use state::State;
+
+    #[test]
+    fn open_list_order() {
+        let node = |f, g, h| OpenNode { f, g, h, node: () };
+        let mut open = BinaryHeap::new();
+        open.push(node(6.0, 2, 4));
+        open.push(node(5.0, 1, 4));
+        open.push(node(6.0, 4, 2));
+        open.push(node(6.0, 3, 2));
+        let order = std::iter::from_fn(|| open.pop().map(|n| (n.g, n.h))).collect::<Vec<_>>();
+        assert_eq!(order, vec![(1, 4), (4, 2), (3, 2), (2, 4)]);
+        assert!(node(6.0, 4, 2) != node(6.0, 3, 2));
+    }
+
+    /// A counter that must reach 10 by adding 1, 3 or removing 1.
+    #[derive(Hash, Clone, PartialEq, Eq, Debug)]
+    struct Counter(i32);
+
+    impl DistNode<i32> for Counter {
+        fn dist_from_end(&self) -> i32 {
+            ((10 - self.0).abs() + 2) / 3
+        }
+        fn moves(&self) -> Vec<i32> {
+            vec![1, -1, 3]
+        }
+        fn modify(&mut self, mv: &i32) {
+            self.0 += *mv;
+        }
+        fn end(&self) -> bool {
+            self.0 == 10
+        }
+        fn cost_to(&self, _target: &Self) -> i32 {
+            1
+        }
+    }
+
+    /// A position on a ring of 50 cells that must reach the cell 23,
+    /// by steps of 1 or 5 cells in both directions.
+    #[derive(Hash, Clone, PartialEq, Eq, Debug)]
+    struct Ring(i32);
+
+    impl DistNode<i32> for Ring {
+        fn dist_from_end(&self) -> i32 {
+            0
+        }
+        fn moves(&self) -> Vec<i32> {
+            vec![1, -1, 5, -5]
+        }
+        fn modify(&mut self, mv: &i32) {
+            self.0 = (self.0 + mv).rem_euclid(50);
+        }
+        fn end(&self) -> bool {
+            self.0 == 23
+        }
+        fn cost_to(&self, _target: &Self) -> i32 {
+            1
+        }
+    }
+
+    impl ReversibleNode<i32> for Ring {
+        fn reverse_move(&self, mv: &i32) -> i32 {
+            -mv
+        }
+        fn final_node(&self) -> Self {
+            Ring(23)
+        }
+    }
+
+    #[test]
+    fn bidirectional_bfs_is_optimal() {
+        for start in 0..50 {
+            let moves = bidirectional_bfs(Ring(start)).unwrap();
+            assert_eq!(moves.len(), bfs(Ring(start)).unwrap().len());
+            assert_eq!((start + moves.iter().sum::<i32>()).rem_euclid(50), 23);
+        }
+    }
+
+    #[test]
+    fn bidirectional_keeps_a_meeting_when_stopped() {
+        let mut state = State::new_perfect(3);
+        state.shuffle(40, 1);
+        let source = PackedState::from(&state);
+        let solved = Bidirectional::new(source.clone()).solve().unwrap();
+        // stopped before the last state of the layer where the two sides met.
+        let limits = Limits::new().max_nodes(solved.stats.expanded - 1);
+        match Bidirectional::new(source).solve_within(&limits) {
+            Outcome::LimitReached(Limit::Nodes, partial) => {
+                assert!(partial.moves.len() >= solved.moves.len());
+                assert_eq!(verify_solution(&state, &MoveSeq::new(partial.moves)), Ok(()));
+            }
+            outcome => panic!("{:?}", outcome),
+        }
+    }
+
+    #[test]
+    fn iddfs_is_optimal() {
+        let bfs_len = bfs(Counter(0)).unwrap().len();
+        let moves = iddfs(Counter(0)).unwrap();
+        assert_eq!(moves.len(), bfs_len);
+        assert_eq!(moves.iter().sum::<i32>(), 10);
+        assert!(depth_limited::<i32, _>(Counter(0), bfs_len - 1).is_none());
+        let longer = depth_limited(Counter(0), bfs_len + 3).unwrap();
+        assert!(longer.len() <= bfs_len + 3);
+        assert_eq!(longer.iter().sum::<i32>(), 10);
+        for start in 0..50 {
+            let moves = iddfs(Ring(start)).unwrap();
+            assert_eq!(moves.len(), bfs(Ring(start)).unwrap().len());
+        }
+
+        let mut state = State::new_perfect(3);
+        state.shuffle(12, 3);
+        let solution: Solution<Direction> = Iddfs::new(PackedState::from(&state)).solve().unwrap();
+        assert_eq!(solution.moves.len(), bfs(state).unwrap().len());
+        // only the current path is kept.
+        assert_eq!(solution.stats.peak_open, solution.moves.len() + 1);
+    }
+
+    #[test]
+    fn ida_star_is_optimal() {
+        let bfs_len = bfs(Counter(0)).unwrap().len();
+        let ida = ida_star(Counter(0)).unwrap();
+        assert_eq!(ida.len(), bfs_len);
+        assert_eq!(ida.iter().sum::<i32>(), 10);
+    }
+
+    #[test]
+    fn astar_is_optimal() {
+        let bfs_len = bfs(Counter(-7)).unwrap().len();
+        let path = AStar::new(Counter(-7)).solve().unwrap().moves;
+        assert_eq!(path.len(), bfs_len);
+        assert_eq!(path.iter().sum::<i32>(), 17);
+    }
+
+    #[test]
+    fn parallel_astar_is_optimal() {
+        let optimal = bfs(Counter(-7)).unwrap().len();
+        for threads in 1..5 {
+            let counter: Solution<i32> = ParallelAStar::new(Counter(-7)).threads(threads).solve().unwrap();
+            assert_eq!(counter.moves.len(), optimal);
+            assert_eq!(counter.moves.iter().sum::<i32>(), 17);
+            for seed in 0..3 {
+                let mut state = State::new_perfect(3);
+                state.shuffle(40, seed);
+                let sequential = AStar::new(PackedState::from(&state)).solve().unwrap();
+                let parallel = ParallelAStar::new(PackedState::from(&state)).threads(threads).solve().unwrap();
+                assert_eq!(parallel.optimality, Optimality::Optimal);
+                assert_eq!(parallel.moves.len(), sequential.moves.len());
+                assert_eq!(verify_solution(&state, &MoveSeq::new(parallel.moves)), Ok(()));
+            }
+        }
+        let limits = Limits::new().max_nodes(50);
+        match ParallelAStar::new(Counter(-1000)).threads(3).solve_within(&limits) {
+            Outcome::LimitReached(Limit::Nodes, partial) => {
+                assert!(partial.lower_bound > 0 && partial.lower_bound <= 337);
+                assert!(partial.moves.iter().sum::<i32>() > 0);
+            }
+            outcome => panic!("{:?}", outcome),
+        }
+    }
+
+    #[test]
+    fn parallel_astar_keeps_cheaper_solutions() {
+        // the final state was found with a cost of 12, then reached again with a cost of 10.
+        let mut workers: Vec<Worker<i32, Counter>> = (0..2).map(Worker::new).collect();
+        let end = Counter(10);
+        let id = owner(&end, 2);
+        workers[owner(&Counter(0), 2)].reached.insert(Counter(0), (0, None));
+        workers[id].reached.insert(end.clone(), (10, Some((Counter(0), 10))));
+        workers[1 - id].found = Some(end.clone());
+        assert_eq!(cheapest_found(&workers, 12), Some((10, &end)));
+        assert_eq!(path_through(&workers, &end), vec![10]);
+        // a solution stops the search as soon as any limit is reached.
+        for threads in 2..5 {
+            for nodes in 1..40 {
+                let limits = Limits::new().max_nodes(nodes);
+                match ParallelAStar::new(Counter(-7)).threads(threads).solve_within(&limits) {
+                    Outcome::Solved(solution) => assert_eq!(solution.moves.iter().sum::<i32>(), 17),
+                    Outcome::LimitReached(Limit::Nodes, _) => {}
+                    outcome => panic!("{:?}", outcome),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn searches_count_their_work() {
+        let bfs: Solution<i32> = Bfs::new(Counter(0)).solve().unwrap();
+        let astar: Solution<i32> = AStar::new(Counter(0)).solve().unwrap();
+        let ida: Solution<i32> = IdaStar::new(Counter(0)).solve().unwrap();
+        for stats in [bfs.stats, astar.stats, ida.stats].iter() {
+            // each counter has three moves.
+            assert_eq!(stats.generated, 3 * stats.expanded);
+            assert!(stats.duplicates <= stats.generated);
+            assert!(stats.peak_open > 0);
+            assert!(stats.peak_memory >= stats.peak_open * size_of::<Counter>());
+        }
+        assert_eq!(bfs.stats.heuristic_evals, 0);
+        // the source and every state reached by a cheaper path are evaluated once.
+        assert_eq!(
+            astar.stats.heuristic_evals,
+            1 + astar.stats.generated - astar.stats.duplicates
+        );
+        assert!(astar.stats.expanded < bfs.stats.expanded);
+        assert!(ida.stats.heuristic_evals > ida.stats.expanded);
+    }
+
+    #[test]
+    fn bfs_expands_each_state_once() {
+        // the 12 states of the 2x2 board reachable from an unsolvable one.
+        let unsolvable: State = "2.1:3.0".parse().unwrap();
+        let outcome = Bfs::new(PackedState::from(&unsolvable)).solve_within(&Limits::new());
+        let stats = outcome.stats();
+        assert_eq!(stats.expanded, 12);
+        assert_eq!(stats.generated - stats.duplicates, 11);
+    }
+
+    #[test]
+    fn weighted_astar_respects_its_bound() {
+        for seed in 0..4 {
+            let mut state = State::new_perfect(3);
+            state.shuffle(60, seed);
+            let optimal = ida_star(PackedState::from(&state)).unwrap().len();
+            let weighted = AStar::new(PackedState::from(&state)).weight(2.0).solve().unwrap();
+            assert_eq!(weighted.optimality, Optimality::Bounded(2.0));
+            assert!(weighted.moves.len() <= 2 * optimal);
+
+            let mut improvements: Vec<Improvement<Direction>> = vec![];
+            let mut anytime = AStar::new(PackedState::from(&state)).weight(5.0);
+            let solution = anytime
+                .anytime(&Limits::new(), |improvement| improvements.push(improvement.clone()))
+                .solution()
+                .unwrap();
+            assert_eq!(solution.optimality, Optimality::Optimal);
+            assert_eq!(solution.moves.len(), optimal);
+            assert_eq!(improvements.last().unwrap().moves, solution.moves);
+            for pair in improvements.windows(2) {
+                assert!(pair[1].moves.len() < pair[0].moves.len());
+            }
+            for improvement in improvements.iter() {
+                assert!(improvement.lower_bound as usize <= optimal);
+                let length = improvement.moves.len() as f64;
+                assert!(improvement.bound >= 1.0 && length <= improvement.bound * optimal as f64);
+            }
+        }
+    }
+}
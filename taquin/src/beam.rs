@@ -0,0 +1,179 @@
+//! Beam search : a breadth first search keeping only the most promising states of each depth.
+//!
+//! The states of each depth are ranked by their estimated distance to the end and
+//! only the best ones, as many as the width of the beam, are expanded. The memory
+//! and time needed grow with the width and the length of the solution, so the search
+//! trades time for shorter solutions. The solutions are not the shortest, and a beam
+//! too narrow can run out of new states before reaching the end.
+//!
+//! On large boards a single search gets lost among the states where the same tiles
+//! are out of place : the reducer then places the lines of the board one by one with
+//! beam searches, see *Reducer::beam*.
+
+use std::collections::HashSet;
+use std::hash::Hash;
+use std::time::Instant;
+use astar::DistNode;
+use solver::{Limits, Optimality, Outcome, Partial, SearchStats, Solution, Solver};
+
+/// The width used when none is given : it solves 10x10 boards in a few seconds.
+pub const DEFAULT_WIDTH: usize = 1000;
+
+/// A state waiting to be ranked : its estimate, the index of its parent
+/// in the previous depth and the move from it.
+type Candidate<U, T> = (i32, usize, U, T);
+
+#[derive(Debug)]
+/// The beam search struct : the source and the width of the beam.
+/// The states are ranked with their own heuristic, set on the source.
+pub struct BeamSearch<T> {
+    source: T,
+    width: usize,
+}
+
+impl<T> BeamSearch<T> {
+    /// Creates a beam search from the source state, with the default width.
+    pub fn new(source: T) -> Self {
+        BeamSearch {
+            source,
+            width: DEFAULT_WIDTH,
+        }
+    }
+    /// Sets the number of states kept at each depth, at least 1.
+    pub fn width(mut self, width: usize) -> Self {
+        assert!(width >= 1, "the width of the beam must be at least 1");
+        self.width = width;
+        self
+    }
+}
+
+/// Returns the moves to the state at the given index of the last depth,
+/// following the parents recorded for each depth.
+fn path_back<U: Clone>(parents: &[Vec<(usize, U)>], mut index: usize) -> Vec<U> {
+    let mut moves = vec![];
+    for depth in parents.iter().rev() {
+        let (parent, ref mv) = depth[index];
+        moves.push(mv.clone());
+        index = parent;
+    }
+    moves.reverse();
+    moves
+}
+
+impl<U: Clone, T: DistNode<U> + Hash + Clone + Eq> Solver<U> for BeamSearch<T> {
+    fn name(&self) -> &'static str {
+        "beam"
+    }
+    /// Uses beam search to find a way to the final state.
+    /// The search fails when every state of a depth has already been seen.
+    /// When stopped, the most promising state is the best one of the current depth,
+    /// and the only lower bound known is the estimate of the source.
+    fn solve_within(&mut self, limits: &Limits) -> Outcome<U> {
+        let start = Instant::now();
+        let mut stats = SearchStats::default();
+        let size = self.source.memory_size();
+        let lower_bound = self.source.dist_from_end();
+        stats.heuristic_evals += 1;
+        if self.source.end() {
+            return Outcome::Solved(Solution::new(vec![], Optimality::Feasible, stats, start));
+        }
+        let mut seen = HashSet::new();
+        seen.insert(self.source.clone());
+        let mut beam = vec![self.source.clone()];
+        // the parent and move of each state kept, depth by depth.
+        let mut parents: Vec<Vec<(usize, U)>> = vec![];
+        while !beam.is_empty() {
+            let mut candidates: Vec<Candidate<U, T>> = vec![];
+            for (index, node) in beam.iter().enumerate() {
+                if let Some(stop) = limits.check(&stats) {
+                    let partial = Partial {
+                        moves: path_back(&parents, 0),
+                        lower_bound,
+                        stats: SearchStats {
+                            elapsed: start.elapsed(),
+                            ..stats
+                        },
+                    };
+                    return Outcome::stopped(stop, partial);
+                }
+                stats.expanded += 1;
+                for mv in node.moves() {
+                    let mut next = node.clone();
+                    next.modify(&mv);
+                    stats.generated += 1;
+                    if next.end() {
+                        let mut moves = path_back(&parents, index);
+                        moves.push(mv);
+                        return Outcome::Solved(Solution::new(
+                            moves,
+                            Optimality::Feasible,
+                            stats,
+                            start,
+                        ));
+                    }
+                    if seen.contains(&next) {
+                        stats.duplicates += 1;
+                        continue;
+                    }
+                    stats.heuristic_evals += 1;
+                    candidates.push((next.dist_from_end(), index, mv, next));
+                }
+            }
+            if candidates.len() > self.width {
+                candidates.select_nth_unstable_by_key(self.width - 1, |c| c.0);
+                candidates.truncate(self.width);
+            }
+            candidates.sort_by_key(|c| c.0);
+            let mut depth = Vec::with_capacity(candidates.len());
+            beam.clear();
+            for (_, parent, mv, next) in candidates {
+                if seen.insert(next.clone()) {
+                    depth.push((parent, mv));
+                    beam.push(next);
+                } else {
+                    stats.duplicates += 1;
+                }
+            }
+            parents.push(depth);
+            let recorded: usize = parents.iter().map(|depth| depth.len()).sum();
+            stats.record(
+                beam.len(),
+                seen.len() * size + recorded * size_of::<(usize, U)>(),
+            );
+        }
+        Outcome::Exhausted(SearchStats {
+            elapsed: start.elapsed(),
+            ..stats
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use astar::ida_star;
+    use direction::Direction;
+    use moves::{verify_solution, MoveSeq};
+    use packed::PackedState;
+    use state::State;
+
+    #[test]
+    fn wider_beams_find_shorter_solutions() {
+        let mut state = State::new_perfect(4);
+        state.shuffle(80, 6);
+        let optimal = ida_star(PackedState::from(&state)).unwrap().len();
+        let lengths = [10, 100, 1000]
+            .iter()
+            .map(|&width| {
+                let mut beam = BeamSearch::new(PackedState::from(&state)).width(width);
+                let solution: Solution<Direction> = beam.solve().unwrap();
+                assert_eq!(solution.optimality, Optimality::Feasible);
+                let moves = MoveSeq::new(solution.moves);
+                assert_eq!(verify_solution(&state, &moves), Ok(()));
+                moves.len()
+            })
+            .collect::<Vec<_>>();
+        assert!(lengths.windows(2).all(|pair| pair[1] <= pair[0]), "{:?}", lengths);
+        assert!(lengths[0] > optimal && lengths[2] < 2 * optimal, "{:?} {}", lengths, optimal);
+    }
+}
@@ -0,0 +1,186 @@
+//! Directions of the moves, and their compact notation.
+//!
+//! A move is written as the direction of the empty cell by default : `R` moves the
+//! empty cell right, so the tile on its right slides left. In the tile convention
+//! the same move is written `L`, after the tile.
+//! A sequence of moves is written as a string of letters : `RRDLU`.
+
+use std::error::Error;
+use std::fmt;
+
+/// The direction of a move of the empty cell.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Direction {
+    /// Towards the first row.
+    Up,
+    /// Towards the last row.
+    Down,
+    /// Towards the first column.
+    Left,
+    /// Towards the last column.
+    Right,
+}
+
+/// Whose move a direction describes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Convention {
+    /// The direction of the empty cell, the one used in the library.
+    Blank,
+    /// The direction of the tile that slides into the empty cell, the opposite one.
+    Tile,
+}
+
+impl Direction {
+    /// All the directions, in the order the states list their moves.
+    pub const ALL: [Direction; 4] = [Direction::Up, Direction::Down, Direction::Left, Direction::Right];
+
+    /// Returns the opposite direction, which cancels the move.
+    pub fn inverse(self) -> Direction {
+        match self {
+            Direction::Up => Direction::Down,
+            Direction::Down => Direction::Up,
+            Direction::Left => Direction::Right,
+            Direction::Right => Direction::Left,
+        }
+    }
+    /// Returns the change of row and column of the move.
+    pub fn delta(self) -> (i32, i32) {
+        match self {
+            Direction::Up => (-1, 0),
+            Direction::Down => (1, 0),
+            Direction::Left => (0, -1),
+            Direction::Right => (0, 1),
+        }
+    }
+    /// Returns the direction with the given change of row and column, if any.
+    pub fn from_delta(delta: (i32, i32)) -> Option<Direction> {
+        Direction::ALL.iter().cloned().find(|d| d.delta() == delta)
+    }
+    /// Returns the letter of the direction : `U`, `D`, `L` or `R`.
+    pub fn letter(self) -> char {
+        match self {
+            Direction::Up => 'U',
+            Direction::Down => 'D',
+            Direction::Left => 'L',
+            Direction::Right => 'R',
+        }
+    }
+    /// Returns the direction of the given letter, in upper or lower case.
+    pub fn from_letter(letter: char) -> Option<Direction> {
+        match letter.to_ascii_uppercase() {
+            'U' => Some(Direction::Up),
+            'D' => Some(Direction::Down),
+            'L' => Some(Direction::Left),
+            'R' => Some(Direction::Right),
+            _ => None,
+        }
+    }
+    /// Converts a move of the empty cell to the given convention, or back.
+    pub fn convert(self, convention: Convention) -> Direction {
+        match convention {
+            Convention::Blank => self,
+            Convention::Tile => self.inverse(),
+        }
+    }
+}
+
+impl From<Direction> for (i32, i32) {
+    fn from(direction: Direction) -> (i32, i32) {
+        direction.delta()
+    }
+}
+
+impl fmt::Display for Direction {
+    /// Writes the name of the direction : `UP`, `DOWN`, `LEFT` or `RIGHT`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            Direction::Up => "UP",
+            Direction::Down => "DOWN",
+            Direction::Left => "LEFT",
+            Direction::Right => "RIGHT",
+        })
+    }
+}
+
+impl Convention {
+    /// Returns the convention with the given name : `blank` or `tile`.
+    pub fn from_name(name: &str) -> Option<Convention> {
+        match name {
+            "blank" => Some(Convention::Blank),
+            "tile" => Some(Convention::Tile),
+            _ => None,
+        }
+    }
+}
+
+/// A letter of a sequence of moves which is not a direction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MoveParseError {
+    /// The index of the letter, whitespace excluded, starting at 0.
+    pub position: usize,
+    /// The letter.
+    pub letter: char,
+}
+
+impl fmt::Display for MoveParseError {
+    /// Counts the moves from 1, like *MoveError*.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "error at move {} : {:?} is not one of U, D, L or R",
+            self.position + 1,
+            self.letter
+        )
+    }
+}
+
+impl Error for MoveParseError {}
+
+/// Reads moves written in the given convention, whitespace is ignored.
+/// Returns the moves of the empty cell.
+pub fn parse_moves(text: &str, convention: Convention) -> Result<Vec<Direction>, MoveParseError> {
+    text.chars()
+        .filter(|c| !c.is_whitespace())
+        .enumerate()
+        .map(|(position, letter)| match Direction::from_letter(letter) {
+            Some(direction) => Ok(direction.convert(convention)),
+            None => Err(MoveParseError { position, letter }),
+        })
+        .collect()
+}
+
+/// Writes moves of the empty cell in the given convention.
+pub fn format_moves(moves: &[Direction], convention: Convention) -> String {
+    moves.iter().map(|d| d.convert(convention).letter()).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn notation() {
+        use self::Direction::*;
+        let moves = vec![Right, Right, Down, Left, Up];
+        assert_eq!(format_moves(&moves, Convention::Blank), "RRDLU");
+        assert_eq!(format_moves(&moves, Convention::Tile), "LLURD");
+        assert_eq!(parse_moves("RRDLU", Convention::Blank), Ok(moves.clone()));
+        assert_eq!(parse_moves("ll urd", Convention::Tile), Ok(moves));
+        assert_eq!(
+            parse_moves("RRXU", Convention::Blank),
+            Err(MoveParseError {
+                position: 2,
+                letter: 'X',
+            })
+        );
+        let error = parse_moves("R X", Convention::Blank).unwrap_err();
+        assert_eq!(error.to_string(), "error at move 2 : 'X' is not one of U, D, L or R");
+        for &direction in Direction::ALL.iter() {
+            assert_eq!(Direction::from_delta(direction.into()), Some(direction));
+            assert_eq!(direction.inverse().inverse(), direction);
+            let (x, y) = direction.delta();
+            assert_eq!(direction.inverse().delta(), (-x, -y));
+        }
+        assert_eq!(Direction::from_delta((1, 1)), None);
+    }
+}
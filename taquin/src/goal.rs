@@ -0,0 +1,222 @@
+//! Goals : the arrangements of tiles a puzzle has to reach.
+//!
+//! The usual goal has the tiles in reading order and the empty cell last,
+//! but any arrangement of the board can be used as a goal.
+
+use parse::{check_values, ParseError};
+use state::{Board, State};
+
+/// The final arrangement of the tiles of a board.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Goal {
+    height: usize,
+    width: usize,
+    /// The values of the cells, row by row.
+    cells: Vec<usize>,
+    /// The position of each value.
+    positions: Vec<(usize, usize)>,
+}
+
+impl Goal {
+    /// The tiles in reading order, then the empty cell.
+    pub fn standard(height: usize, width: usize) -> Self {
+        let order = (0..height * width).map(|i| (i / width, i % width)).collect();
+        Goal::along(height, width, order)
+    }
+    /// The empty cell, then the tiles in reading order.
+    pub fn blank_first(height: usize, width: usize) -> Self {
+        let cells = (0..height * width).collect();
+        Goal::new(height, width, cells).unwrap()
+    }
+    /// The tiles row by row, alternately left to right and right to left, then the empty cell.
+    pub fn snake(height: usize, width: usize) -> Self {
+        let order = (0..height * width)
+            .map(|i| {
+                let (x, y) = (i / width, i % width);
+                if x % 2 == 0 {
+                    (x, y)
+                } else {
+                    (x, width - 1 - y)
+                }
+            })
+            .collect();
+        Goal::along(height, width, order)
+    }
+    /// The tiles in a clockwise spiral from the top left corner,
+    /// the empty cell ends the spiral.
+    pub fn spiral(height: usize, width: usize) -> Self {
+        let (mut top, mut bottom, mut left, mut right) = (0, height, 0, width);
+        let mut order = vec![];
+        while top < bottom && left < right {
+            order.extend((left..right).map(|y| (top, y)));
+            order.extend((top + 1..bottom).map(|x| (x, right - 1)));
+            if top + 1 < bottom {
+                order.extend((left..right - 1).rev().map(|y| (bottom - 1, y)));
+            }
+            if left + 1 < right {
+                order.extend((top + 1..bottom - 1).rev().map(|x| (x, left)));
+            }
+            top += 1;
+            bottom -= 1;
+            left += 1;
+            right -= 1;
+        }
+        Goal::along(height, width, order)
+    }
+    /// The tiles column by column, then the empty cell.
+    pub fn column_major(height: usize, width: usize) -> Self {
+        let order = (0..height * width).map(|i| (i % height, i / height)).collect();
+        Goal::along(height, width, order)
+    }
+    /// Returns the goal with the given name, if any :
+    /// `standard`, `blank-first`, `snake`, `spiral` or `column-major`.
+    pub fn from_name(name: &str, height: usize, width: usize) -> Option<Self> {
+        match name {
+            "standard" => Some(Goal::standard(height, width)),
+            "blank-first" => Some(Goal::blank_first(height, width)),
+            "snake" => Some(Goal::snake(height, width)),
+            "spiral" => Some(Goal::spiral(height, width)),
+            "column-major" => Some(Goal::column_major(height, width)),
+            _ => None,
+        }
+    }
+    /// Creates a goal from the values of its cells, row by row.
+    /// Each value from 0 to the number of cells minus one must appear once.
+    pub fn new(height: usize, width: usize, cells: Vec<usize>) -> Result<Self, ParseError> {
+        if height == 0 || width == 0 {
+            return Err(ParseError::InvalidSize(format!("{}x{}", height, width)));
+        }
+        if cells.len() != height * width {
+            return Err(ParseError::WrongCellCount {
+                expected: height * width,
+                found: cells.len(),
+            });
+        }
+        let rows = cells.chunks(width).map(|row| row.to_vec()).collect::<Vec<_>>();
+        check_values(&rows)?;
+        let mut positions = vec![(0, 0); cells.len()];
+        for (i, &value) in cells.iter().enumerate() {
+            positions[value] = (i / width, i % width);
+        }
+        Ok(Goal {
+            height,
+            width,
+            cells,
+            positions,
+        })
+    }
+    /// Places the tiles along the given order of the cells, the empty cell last.
+    fn along(height: usize, width: usize, order: Vec<(usize, usize)>) -> Self {
+        let mut cells = vec![0; height * width];
+        for (value, &(x, y)) in order.iter().enumerate().take(height * width - 1) {
+            cells[x * width + y] = value + 1;
+        }
+        Goal::new(height, width, cells).unwrap()
+    }
+    /// Returns the number of rows.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+    /// Returns the number of columns.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+    /// Returns the values of the cells, row by row.
+    pub fn cells(&self) -> &[usize] {
+        &self.cells
+    }
+    /// Returns the value of the cell at row x and column y, 0 for the empty cell.
+    pub fn cell(&self, x: usize, y: usize) -> usize {
+        self.cells[x * self.width + y]
+    }
+    /// Returns the final position of the given value, 0 for the empty cell.
+    pub fn position(&self, value: usize) -> (usize, usize) {
+        self.positions[value]
+    }
+    /// Checks if the board has reached this goal.
+    pub fn is_reached<B: Board>(&self, board: &B) -> bool {
+        (0..self.height).all(|x| (0..self.width).all(|y| board.cell(x, y) == self.cell(x, y)))
+    }
+}
+
+impl<'a> From<&'a State> for Goal {
+    /// Uses the arrangement of the state as a goal.
+    fn from(state: &'a State) -> Self {
+        Goal::new(state.height, state.width, state.cells()).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use astar::{bfs, bidirectional_bfs, AStar, DistNode};
+    use heuristic::Heuristic;
+    use reducer::Reducer;
+    use solver::Solver;
+
+    fn named(height: usize, width: usize) -> Vec<Goal> {
+        ["standard", "blank-first", "snake", "spiral", "column-major"]
+            .iter()
+            .map(|name| Goal::from_name(name, height, width).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn layouts() {
+        assert_eq!(Goal::standard(2, 3).cells(), &[1, 2, 3, 4, 5, 0]);
+        assert_eq!(Goal::blank_first(2, 3).cells(), &[0, 1, 2, 3, 4, 5]);
+        assert_eq!(Goal::snake(3, 2).cells(), &[1, 2, 4, 3, 5, 0]);
+        assert_eq!(Goal::spiral(3, 3).cells(), &[1, 2, 3, 8, 0, 4, 7, 6, 5]);
+        assert_eq!(Goal::spiral(3, 4).cells(), &[1, 2, 3, 4, 10, 11, 0, 5, 9, 8, 7, 6]);
+        assert_eq!(Goal::column_major(2, 3).cells(), &[1, 3, 5, 2, 4, 0]);
+        assert_eq!(Goal::spiral(3, 3).position(0), (1, 1));
+        assert_eq!(Goal::new(2, 0, vec![]), Err(ParseError::InvalidSize("2x0".to_string())));
+        assert_eq!(Goal::new(0, 0, vec![]), Err(ParseError::InvalidSize("0x0".to_string())));
+        assert_eq!(
+            Goal::new(2, 2, vec![1, 2, 2, 0]),
+            Err(ParseError::Duplicate {
+                value: 2,
+                first: (0, 1),
+                second: (1, 0),
+                missing: vec![3],
+            })
+        );
+    }
+
+    #[test]
+    fn solvers_reach_any_goal() {
+        for goal in named(3, 3) {
+            for times in (10..20).step_by(3) {
+                let mut state = State::new_solved(goal.clone());
+                assert!(state.is_final());
+                state.shuffle(times, times as u64);
+                assert!(state.is_solvable());
+                let optimal = bfs(state.clone()).unwrap();
+                assert_eq!(bidirectional_bfs(state.clone()).unwrap().len(), optimal.len());
+                for heuristic in [Heuristic::Manhattan, Heuristic::LinearConflict].iter() {
+                    state.set_heuristic(heuristic.clone());
+                    assert!(state.dist_from_end() <= optimal.len() as i32);
+                    assert_eq!(AStar::new(state.clone()).solve().unwrap().moves.len(), optimal.len());
+                }
+                let mut solved = state.clone();
+                for mv in optimal {
+                    solved.modify(mv);
+                }
+                assert!(goal.is_reached(&solved));
+            }
+        }
+    }
+
+    #[test]
+    fn reducer_reaches_any_goal() {
+        for goal in named(4, 5) {
+            let mut state = State::new_solved(goal.clone());
+            state.shuffle(200, 0);
+            let moves = Reducer::new(state.clone()).reduce().unwrap();
+            for mv in moves {
+                state.modify(mv);
+            }
+            assert!(state.is_final(), "{:?} : {}", goal, state);
+        }
+    }
+}
@@ -0,0 +1,204 @@
+//! Heuristics : estimations of the number of moves left to solve a state.
+
+use std::sync::Arc;
+use state::Board;
+use pdb::PatternDatabase;
+
+/// The estimations available for the distance from a state to the final state.
+#[derive(Clone, Debug)]
+pub enum Heuristic {
+    /// The number of tiles which are not at their final position.
+    Misplaced,
+    /// The sum of the manhattan distances from each tile to its final position.
+    Manhattan,
+    /// The manhattan distance, plus two moves for each tile that has to leave
+    /// its row or column to let another tile of the same line pass.
+    LinearConflict,
+    /// The sum of the costs stored in an additive pattern database.
+    PatternDb(Arc<PatternDatabase>),
+    /// The first estimate of the library : 10 minus the cells holding their number
+    /// when the board is numbered column by column. It can overestimate.
+    Legacy,
+}
+
+impl Heuristic {
+    /// Returns the heuristic with the given name, if any.
+    /// Pattern databases have to be built, they can not be named.
+    pub fn from_name(name: &str) -> Option<Heuristic> {
+        match name {
+            "misplaced" => Some(Heuristic::Misplaced),
+            "manhattan" => Some(Heuristic::Manhattan),
+            "linear-conflict" => Some(Heuristic::LinearConflict),
+            "legacy" => Some(Heuristic::Legacy),
+            _ => None,
+        }
+    }
+
+    /// Estimates the number of moves needed to solve the state.
+    /// None of the heuristics ever overestimate but the legacy one.
+    pub fn estimate<B: Board>(&self, state: &B) -> i32 {
+        match *self {
+            Heuristic::Misplaced => misplaced(state),
+            Heuristic::Manhattan => manhattan(state),
+            Heuristic::LinearConflict => manhattan(state) + linear_conflicts(state),
+            Heuristic::PatternDb(ref pdb) => pdb.estimate(state),
+            Heuristic::Legacy => legacy(state),
+        }
+    }
+    /// Checks if the heuristic never overestimates, so that A* and IDA* find the
+    /// shortest solutions with it.
+    pub fn is_admissible(&self) -> bool {
+        !matches!(*self, Heuristic::Legacy)
+    }
+}
+
+/// The first estimate : 10 minus the cells whose value is their index in column
+/// order plus one. It ignores the goal, and gives 8 on the solved 3x3 board.
+fn legacy<B: Board>(state: &B) -> i32 {
+    let mut sm = 10;
+    for x in 0..state.height() {
+        for y in 0..state.width() {
+            if state.cell(x, y) == y * state.height() + x + 1 {
+                sm -= 1;
+            }
+        }
+    }
+    sm
+}
+
+/// Counts the tiles which are not at their final position.
+fn misplaced<B: Board>(state: &B) -> i32 {
+    let mut sm = 0;
+    for x in 0..state.height() {
+        for y in 0..state.width() {
+            let value = state.cell(x, y);
+            if value != 0 && state.goal().position(value) != (x, y) {
+                sm += 1;
+            }
+        }
+    }
+    sm
+}
+
+/// Sums the manhattan distances of all tiles to their final positions.
+fn manhattan<B: Board>(state: &B) -> i32 {
+    let mut sm = 0;
+    for x in 0..state.height() {
+        for y in 0..state.width() {
+            let value = state.cell(x, y);
+            if value != 0 {
+                let (gx, gy) = state.goal().position(value);
+                sm += (x as i32 - gx as i32).abs() + (y as i32 - gy as i32).abs();
+            }
+        }
+    }
+    sm
+}
+
+/// Counts the extra moves caused by tiles in conflict in their goal row or column.
+fn linear_conflicts<B: Board>(state: &B) -> i32 {
+    let goal = state.goal();
+    let mut sm = 0;
+    for line in 0..state.height() {
+        // final columns of the tiles of this row that belong to it, left to right.
+        let row = (0..state.width())
+            .map(|y| state.cell(line, y))
+            .filter(|&v| v != 0 && goal.position(v).0 == line)
+            .map(|v| goal.position(v).1)
+            .collect::<Vec<_>>();
+        sm += 2 * line_conflicts(&row);
+    }
+    for line in 0..state.width() {
+        // final rows of the tiles of this column that belong to it, top to bottom.
+        let col = (0..state.height())
+            .map(|x| state.cell(x, line))
+            .filter(|&v| v != 0 && goal.position(v).1 == line)
+            .map(|v| goal.position(v).0)
+            .collect::<Vec<_>>();
+        sm += 2 * line_conflicts(&col);
+    }
+    sm
+}
+
+/// Returns the minimum number of tiles to remove from a line so that the
+/// remaining ones are in the order of their final positions.
+/// This is the length of the line minus its longest increasing subsequence.
+fn line_conflicts(goals: &[usize]) -> i32 {
+    let mut longest = vec![1; goals.len()];
+    for i in 0..goals.len() {
+        for j in 0..i {
+            if goals[j] < goals[i] && longest[j] + 1 > longest[i] {
+                longest[i] = longest[j] + 1;
+            }
+        }
+    }
+    (goals.len() - longest.iter().cloned().max().unwrap_or(0)) as i32
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use state::State;
+    use astar::{bfs, AStar, DistNode};
+    use solver::Solver;
+    use std::collections::{HashMap, VecDeque};
+
+    /// Computes the exact distance to the final state of every 3x3 state.
+    fn distances() -> HashMap<State, i32> {
+        let perfect = State::new_perfect(3);
+        let mut dist = HashMap::new();
+        let mut f = VecDeque::new();
+        dist.insert(perfect.clone(), 0);
+        f.push_back(perfect);
+        while let Some(nouv) = f.pop_front() {
+            let d = dist[&nouv];
+            for mv in nouv.moves() {
+                let mut cop = nouv.clone();
+                cop.modify(mv);
+                if !dist.contains_key(&cop) {
+                    dist.insert(cop.clone(), d + 1);
+                    f.push_back(cop);
+                }
+            }
+        }
+        dist
+    }
+
+    #[test]
+    fn heuristics_are_admissible() {
+        let dist = distances();
+        assert_eq!(dist.len(), 181440);
+        for (state, d) in dist {
+            let misplaced = Heuristic::Misplaced.estimate(&state);
+            let manhattan = Heuristic::Manhattan.estimate(&state);
+            let conflict = Heuristic::LinearConflict.estimate(&state);
+            assert!(misplaced <= manhattan && manhattan <= conflict);
+            assert!(conflict <= d, "{:?} estimated {} for {}", state, conflict, d);
+        }
+    }
+
+    #[test]
+    fn legacy_estimate_is_kept() {
+        let perfect = State::new_perfect(3);
+        assert_eq!(Heuristic::Legacy.estimate(&perfect), 8);
+        assert!(!Heuristic::Legacy.is_admissible() && Heuristic::Manhattan.is_admissible());
+        let mut state = perfect.clone();
+        state.shuffle(20, 1);
+        state.set_heuristic(Heuristic::from_name("legacy").unwrap());
+        let mut solved = state.clone();
+        for mv in AStar::new(state).solve().unwrap().moves {
+            solved.modify(mv);
+        }
+        assert_eq!(solved, perfect);
+    }
+
+    #[test]
+    fn astar_is_optimal_with_linear_conflict() {
+        let mut state = State::new_perfect(3);
+        state.shuffle(40, 0);
+        state.set_heuristic(Heuristic::LinearConflict);
+        assert!(state.dist_from_end() > 0);
+        let optimal = bfs(state.clone()).unwrap().len();
+        assert_eq!(AStar::new(state).solve().unwrap().moves.len(), optimal);
+    }
+}
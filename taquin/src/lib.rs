@@ -22,5 +22,17 @@ extern crate rand;
 extern crate clap;
 
 pub mod state;
+pub mod goal;
+pub mod direction;
+pub mod moves;
+pub mod scramble;
 pub mod reducer;
 pub mod astar;
+pub mod beam;
+pub mod smastar;
+pub mod heuristic;
+pub mod pdb;
+pub mod packed;
+pub mod parse;
+pub mod path;
+pub mod solver;
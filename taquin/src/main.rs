@@ -17,19 +17,29 @@
     unused_import_braces,
     unused_qualifications)]
 
-extern crate time;
-extern crate rand;
 extern crate clap;
+extern crate taquin;
 
-mod state;
-pub mod reducer;
-pub mod astar;
-
-use astar::{DistNode, AStar};
-use reducer::Reducer;
-use clap::{Arg, App};
-use state::State;
-use std::num::ParseIntError;
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+use taquin::state::State;
+use taquin::goal::Goal;
+use taquin::direction::{format_moves, parse_moves, Convention, Direction};
+use taquin::moves::{verify_solution, MoveSeq};
+use taquin::scramble::{random_at_distance, random_solvable, random_walk, seeded_rng};
+use taquin::parse::{parse_grid_rect, ParseError};
+use taquin::heuristic::Heuristic;
+use taquin::pdb::{PatternDatabase, PatternDatabaseBuilder};
+use taquin::path::path_problem;
+use taquin::solver::{create_solver, Limits, Optimality, Outcome, Partial, Solver, SOLVERS};
+use taquin::astar::{AStar, ParallelAStar};
+use taquin::beam::DEFAULT_WIDTH;
+use taquin::reducer::Reducer;
+use taquin::smastar::{SmaStar, DEFAULT_BUDGET};
+use taquin::packed::PackedState;
+use std::ops::RangeInclusive;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
 
 macro_rules! eprintln {
     ($($tt:tt)*) => {{
@@ -50,139 +60,594 @@ pub fn main() {
                 Example usage :\
                     $ taquin 2.3.4:7.1.6:0.8.5",
         )
+        .setting(AppSettings::SubcommandsNegateReqs)
         .arg(
             Arg::with_name("size")
                 .short("s")
                 .long("size")
                 .value_name("SIZE")
-                .help("The size of a column")
-                .takes_value(true),
+                .help("The size of the grid, N for a square one or HxW for H rows of W cells")
+                .takes_value(true)
+                .global(true),
+        )
+        .arg(
+            Arg::with_name("algorithm")
+                .short("a")
+                .long("algorithm")
+                .value_name("ALGORITHM")
+                .help("The search algorithm to use")
+                .possible_values(&SOLVERS)
+                .takes_value(true)
+                .global(true),
+        )
+        .arg(
+            Arg::with_name("heuristic")
+                .long("heuristic")
+                .value_name("HEURISTIC")
+                .help("The distance estimation used by astar, idastar, smastar and beam")
+                .possible_values(&["misplaced", "manhattan", "linear-conflict", "pdb", "legacy"])
+                .takes_value(true)
+                .global(true),
+        )
+        .arg(
+            Arg::with_name("pdb-file")
+                .long("pdb-file")
+                .value_name("FILE")
+                .help("Where the pattern database is loaded from, or saved to once built")
+                .takes_value(true)
+                .global(true),
+        )
+        .arg(
+            Arg::with_name("convention")
+                .long("convention")
+                .value_name("CONVENTION")
+                .help("Whether the moves printed are the ones of the empty cell or of the tiles")
+                .possible_values(&["blank", "tile"])
+                .takes_value(true)
+                .global(true),
+        )
+        .arg(
+            Arg::with_name("weight")
+                .long("weight")
+                .value_name("WEIGHT")
+                .help(
+                    "Multiplies the heuristic of astar by this weight, at least 1 : the solutions \
+                     are found faster but can be up to this many times longer than the shortest",
+                )
+                .takes_value(true)
+                .global(true),
+        )
+        .arg(
+            Arg::with_name("threads")
+                .long("threads")
+                .value_name("THREADS")
+                .help(
+                    "Runs astar on this many threads, each owning a share of the states, \
+                     1 by default",
+                )
+                .conflicts_with_all(&["weight", "anytime"])
+                .takes_value(true)
+                .global(true),
+        )
+        .arg(
+            Arg::with_name("budget")
+                .long("budget")
+                .value_name("STATES")
+                .help(
+                    "The states smastar keeps in memory at once : the solutions are the \
+                     shortest as long as the path to them fits",
+                )
+                .takes_value(true)
+                .global(true),
+        )
+        .arg(
+            Arg::with_name("beam-width")
+                .long("beam-width")
+                .value_name("WIDTH")
+                .help(
+                    "The states kept at each depth by the beam search : wider beams find \
+                     shorter solutions but take more time and memory",
+                )
+                .takes_value(true)
+                .global(true),
+        )
+        .arg(
+            Arg::with_name("anytime")
+                .long("anytime")
+                .help(
+                    "Uses anytime astar : prints the solutions of the weighted search as they \
+                     improve, until the shortest one is found or the time is up",
+                )
+                .global(true),
+        )
+        .arg(
+            Arg::with_name("max-nodes")
+                .long("max-nodes")
+                .value_name("NODES")
+                .help("Stops the search after expanding this number of states")
+                .takes_value(true)
+                .global(true),
+        )
+        .arg(
+            Arg::with_name("max-memory")
+                .long("max-memory")
+                .value_name("MB")
+                .help("Stops the search once the states it stores take this many megabytes")
+                .takes_value(true)
+                .validator(|megabytes| match parse_megabytes(&megabytes) {
+                    Some(_) => Ok(()),
+                    None => Err("the maximum memory must be a positive integer of megabytes \
+                                 that fits in memory"
+                        .to_string()),
+                })
+                .global(true),
+        )
+        .arg(
+            Arg::with_name("timeout")
+                .long("timeout")
+                .value_name("SECONDS")
+                .help("Stops the search after this number of seconds")
+                .takes_value(true)
+                .global(true),
+        )
+        .arg(
+            Arg::with_name("stats")
+                .long("stats")
+                .value_name("FORMAT")
+                .help("Prints what the search went through, as text or as a JSON object")
+                .possible_values(&["text", "json"])
+                .takes_value(true)
+                .global(true),
+        )
+        .arg(
+            Arg::with_name("goal")
+                .long("goal")
+                .value_name("GOAL")
+                .help(
+                    "The arrangement to reach : standard, blank-first, snake, spiral, \
+                     column-major or a grid",
+                )
+                .takes_value(true)
+                .global(true),
         )
         .arg(Arg::with_name("grid").index(1).required(true).takes_value(
             true,
         ))
+        .subcommand(
+            SubCommand::with_name("path")
+                .about("Finds the shortest sequence of moves from one grid to another")
+                .arg(Arg::with_name("from").index(1).required(true).takes_value(true))
+                .arg(Arg::with_name("to").index(2).required(true).takes_value(true)),
+        )
+        .subcommand(
+            SubCommand::with_name("verify")
+                .about("Checks that a sequence of moves like RRDLU solves the grid")
+                .arg(Arg::with_name("grid").index(1).required(true).takes_value(true))
+                .arg(Arg::with_name("moves").index(2).required(true).takes_value(true)),
+        )
+        .subcommand(
+            SubCommand::with_name("generate")
+                .about("Prints random solvable grids, the same seed giving the same grids")
+                .arg(
+                    Arg::with_name("seed")
+                        .long("seed")
+                        .value_name("N")
+                        .help("The seed of the random numbers, 0 by default")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("count")
+                        .long("count")
+                        .value_name("COUNT")
+                        .help("The number of grids, 1 by default")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("moves")
+                        .long("moves")
+                        .value_name("MOVES")
+                        .help(
+                            "Scrambles the goal with this number of random moves instead of \
+                             drawing uniformly random grids",
+                        )
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("distance")
+                        .long("distance")
+                        .value_name("DISTANCE")
+                        .help(
+                            "Only prints grids whose optimal solution has this length, \
+                             or a length in the range MIN-MAX, certified with IDA* and the heuristic",
+                        )
+                        .conflicts_with("moves")
+                        .takes_value(true),
+                ),
+        )
         .get_matches();
 
-    let size = matches.value_of("size").unwrap_or("3").to_string();
+    if let Some(path) = matches.subcommand_matches("path") {
+        return find_path(path);
+    }
+    if let Some(verify) = matches.subcommand_matches("verify") {
+        return verify_moves(verify);
+    }
+    if let Some(generate) = matches.subcommand_matches("generate") {
+        return generate_grids(generate);
+    }
+
+    match create_problem(&matches) {
+        Ok(state) => {
+            if !state.is_solvable() {
+                return eprintln!("{}", explain_unsolvable(&state));
+            }
+            solve(state, &matches)
+        }
+        Err(e) => eprintln!("{}", e),
+    };
+}
 
+/// Reads the grid with the size and the goal given in the options.
+fn create_problem(matches: &ArgMatches) -> Result<State, String> {
+    let size = matches.value_of("size").unwrap_or("3").to_string();
     let grid = matches.value_of("grid").unwrap().to_string();
+    let goal = matches.value_of("goal").unwrap_or("standard");
+    let mut state = create_state(size, grid).map_err(|e| e.to_string())?;
+    state.set_goal(create_goal(goal, &state)?);
+    Ok(state)
+}
 
-    match create_state(size, grid) {
-        Ok(state) => find(state, false),
+/// Checks the moves of the verify subcommand on its grid.
+fn verify_moves(matches: &ArgMatches) {
+    let convention = matches
+        .value_of("convention")
+        .and_then(Convention::from_name)
+        .unwrap_or(Convention::Blank);
+    let moves = match parse_moves(matches.value_of("moves").unwrap(), convention) {
+        Ok(moves) => MoveSeq::new(moves),
+        Err(e) => return eprintln!("{}", e),
+    };
+    match create_problem(matches) {
+        Ok(state) => match verify_solution(&state, &moves) {
+            Ok(()) => println!("The {} moves solve the grid", moves.len()),
+            Err(e) => println!("Sorry\n{}", e),
+        },
         Err(e) => eprintln!("{}", e),
+    }
+}
+
+/// Finds the path between the two grids of the path subcommand.
+fn find_path(matches: &ArgMatches) {
+    let size = matches.value_of("size").unwrap_or("3");
+    let from = create_state(size.to_string(), matches.value_of("from").unwrap().to_string());
+    let to = create_state(size.to_string(), matches.value_of("to").unwrap().to_string());
+    match (from, to) {
+        (Ok(from), Ok(to)) => match path_problem(&from, &to) {
+            Ok(state) => solve(state, matches),
+            Err(e) => eprintln!("Sorry\n{}", e),
+        },
+        (Err(e), _) | (_, Err(e)) => eprintln!("{}", e),
+    }
+}
+
+/// Prints the grids of the generate subcommand.
+fn generate_grids(matches: &ArgMatches) {
+    let seed = match matches.value_of("seed").map_or(Ok(0), |n| n.parse()) {
+        Ok(seed) => seed,
+        Err(_) => return eprintln!("Please input the seed as a positive integer"),
+    };
+    let count = match matches.value_of("count").map_or(Ok(1), |n| n.parse()) {
+        Ok(count) => count,
+        Err(_) => return eprintln!("Please input the count as a positive integer"),
+    };
+    let moves = match matches.value_of("moves").map(|n| n.parse()) {
+        Some(Err(_)) => return eprintln!("Please input the moves as a positive integer"),
+        moves => moves.map(Result::unwrap),
+    };
+    let (height, width) = match create_dimensions(matches.value_of("size").unwrap_or("3")) {
+        Ok(dimensions) => dimensions,
+        Err(e) => return eprintln!("{}", e),
     };
+    let goal = match create_goal(
+        matches.value_of("goal").unwrap_or("standard"),
+        &State::new_perfect_rect(height, width),
+    ) {
+        Ok(goal) => goal,
+        Err(e) => return eprintln!("{}", e),
+    };
+    let distances = match matches.value_of("distance").map(parse_distances) {
+        Some(Err(e)) => return eprintln!("{}", e),
+        distances => distances.map(Result::unwrap),
+    };
+    let heuristic = match create_heuristic(
+        matches.value_of("heuristic").unwrap_or("linear-conflict"),
+        &State::new_solved(goal.clone()),
+        matches.value_of("pdb-file"),
+    ) {
+        Ok(heuristic) => heuristic,
+        Err(e) => return eprintln!("{}", e),
+    };
+    if distances.is_some() && !heuristic.is_admissible() {
+        return eprintln!("Please certify the distances with a heuristic that never overestimates");
+    }
+    let mut rng = seeded_rng(seed);
+    for _ in 0..count {
+        let state = match (moves, distances.clone()) {
+            (_, Some(distances)) => {
+                match random_at_distance(&goal, &heuristic, distances, &mut rng) {
+                    Some((state, _)) => state,
+                    None => return eprintln!("No grid at this distance could be found"),
+                }
+            }
+            (Some(moves), None) => {
+                let mut state = State::new_solved(goal.clone());
+                random_walk(&mut state, moves, &mut rng);
+                state
+            }
+            (None, None) => random_solvable(&goal, &mut rng),
+        };
+        println!("{}", state);
+    }
 }
 
-/// Creates a state and checks that there are no errors.
-/// takes the strings passed as config.
-pub fn create_state(s_size: String, s_state: String) -> Result<State, String> {
-    let mut size = 3;
-    match s_size.parse() {
-        Ok(s) => size = s,
-        Err(_) => return Err("Please input the size as an integer".to_string()),
+/// Reads a distance, or an inclusive range of distances : MIN-MAX.
+fn parse_distances(text: &str) -> Result<RangeInclusive<usize>, String> {
+    let bounds = text.split('-').map(|d| d.trim().parse()).collect::<Vec<_>>();
+    match bounds[..] {
+        [Ok(d)] => Ok(d..=d),
+        [Ok(min), Ok(max)] if min <= max => Ok(min..=max),
+        _ => Err(format!("Please input the distance as N or MIN-MAX, not {}", text)),
     }
-    let state = match parse_grid(s_state, size) {
-        Ok((grid, pos)) => State::new(pos.0, pos.1, grid, size),
-        Err(e) => return Err(e),
+}
+
+/// Sets the heuristic given in the options and solves the state with the given algorithm.
+fn solve(mut state: State, matches: &ArgMatches) {
+    let algorithm = matches.value_of("algorithm").unwrap_or("bfs");
+    let heuristic = matches.value_of("heuristic").unwrap_or("linear-conflict");
+    let pdb_file = matches.value_of("pdb-file");
+    let convention = matches
+        .value_of("convention")
+        .and_then(Convention::from_name)
+        .unwrap_or(Convention::Blank);
+    let limits = match create_limits(matches) {
+        Ok(limits) => limits,
+        Err(e) => return eprintln!("{}", e),
     };
-    Ok(state)
+    let weight = match matches.value_of("weight").map_or(Ok(1.0), |w| w.parse()) {
+        Ok(weight) if weight >= 1.0 => weight,
+        _ => return eprintln!("Please input the weight as a number of at least 1"),
+    };
+    let width = match matches.value_of("beam-width").map_or(Ok(DEFAULT_WIDTH), |w| w.parse()) {
+        Ok(width) if width >= 1 => width,
+        _ => return eprintln!("Please input the beam width as a positive integer"),
+    };
+    let budget = match matches.value_of("budget").map_or(Ok(DEFAULT_BUDGET), |b| b.parse()) {
+        Ok(budget) if budget >= 1 => budget,
+        _ => return eprintln!("Please input the budget as a positive integer"),
+    };
+    let threads = match matches.value_of("threads").map_or(Ok(1), |t| t.parse()) {
+        Ok(threads) if threads >= 1 => threads,
+        _ => return eprintln!("Please input the threads as a positive integer"),
+    };
+    match create_heuristic(heuristic, &state, pdb_file) {
+        Ok(heuristic) => state.set_heuristic(heuristic),
+        Err(e) => return eprintln!("{}", e),
+    }
+    let stats = matches.value_of("stats");
+    if matches.is_present("anytime") {
+        return find_anytime(state, weight, convention, stats, &limits);
+    }
+    find(state, algorithm, (weight, width, threads, budget), convention, stats, &limits)
 }
 
-/// Creates a grid/position and checks that there are no errors.
-/// takes the strings passed as config.
-pub fn parse_grid(
-    s_size: String,
-    size: usize,
-) -> Result<(Vec<Vec<usize>>, (usize, usize)), String> {
-    // parse a grid of results.
-    let p_grid = s_size
-        .split(":")
-        .map(|line| {
-            line.split(".").map(|e| e.parse()).collect::<Vec<
-                Result<
-                    usize,
-                    ParseIntError,
-                >,
-            >>()
-        })
-        .collect::<Vec<_>>();
+/// Creates the limits of the search given in the options.
+/// The deadline is counted from now.
+fn create_limits(matches: &ArgMatches) -> Result<Limits, String> {
+    let mut limits = Limits::new();
+    if let Some(nodes) = matches.value_of("max-nodes") {
+        let nodes = nodes.parse().map_err(|_| "Please input the maximum nodes as a positive integer")?;
+        limits = limits.max_nodes(nodes);
+    }
+    if let Some(megabytes) = matches.value_of("max-memory") {
+        let bytes = parse_megabytes(megabytes)
+            .ok_or("Please input the maximum memory as a positive integer")?;
+        limits = limits.max_memory(bytes);
+    }
+    if let Some(seconds) = matches.value_of("timeout") {
+        let timeout = seconds
+            .parse()
+            .ok()
+            .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+            .ok_or("Please input the timeout as a positive number of seconds")?;
+        limits = limits.timeout(timeout);
+    }
+    Ok(limits)
+}
 
+/// Reads a number of megabytes and returns it in bytes, or None if it overflows.
+fn parse_megabytes(megabytes: &str) -> Option<usize> {
+    megabytes.parse::<usize>().ok()?.checked_mul(1024 * 1024)
+}
 
+/// Explains why the state can not be solved.
+pub fn explain_unsolvable(state: &State) -> String {
+    format!(
+        "Sorry\nThis grid can not be solved : it is {} swaps of two cells away from the goal \
+         and its empty cell is {} steps away from its final position. Each move swaps the \
+         empty cell with a tile and moves it one step, so both numbers must have the same parity.",
+        state.swaps_to_goal(),
+        state.blank_distance()
+    )
+}
 
-    // check that only integers were given in the grid.
-    let mut grid = vec![];
-    let mut zero = None;
+/// Creates the goal with the given name, or reads it as a grid of the size of the state.
+pub fn create_goal(text: &str, state: &State) -> Result<Goal, String> {
+    if let Some(goal) = Goal::from_name(text, state.height, state.width) {
+        return Ok(goal);
+    }
+    let layout = parse_grid_rect(text, state.height, state.width).map_err(|e| e.to_string())?;
+    Ok(Goal::from(&layout))
+}
 
-    for (id_x, p_line) in p_grid.iter().enumerate() {
-        let mut line = vec![];
-        for (id_y, j) in p_line.iter().enumerate() {
-            match j {
-                &Ok(val) => {
-                    if val == 0 {
-                        zero = Some((id_x, id_y));
-                    }
-                    line.push(val)
-                }
-                &Err(_) => {
-                    return Err(format!(
-                        "error at position : {}, {} cannot parse integer",
-                        id_x,
-                        id_y
-                    ))
-                }
-            }
+/// Creates the heuristic with the given name for the given state.
+/// Pattern databases are loaded from the file if it exists, otherwise they are
+/// built with the default partition of the board and saved to the file.
+pub fn create_heuristic(
+    name: &str,
+    state: &State,
+    pdb_file: Option<&str>,
+) -> Result<Heuristic, String> {
+    if name != "pdb" {
+        return Heuristic::from_name(name).ok_or(format!("Unknown heuristic : {}", name));
+    }
+    if let Some(path) = pdb_file {
+        if Path::new(path).exists() {
+            let pdb = PatternDatabase::load_file(path).map_err(|e| e.to_string())?;
+            pdb.check(state).map_err(|e| e.to_string())?;
+            return Ok(Heuristic::PatternDb(Arc::new(pdb)));
         }
-        grid.push(line);
     }
+    let pdb = PatternDatabaseBuilder::new_rect(state.height, state.width)
+        .goal(state.goal().clone())
+        .progress(|p| {
+            eprintln!(
+                "pattern {}/{} : depth {}, {}/{} placements",
+                p.pattern + 1,
+                p.patterns,
+                p.depth,
+                p.reached,
+                p.placements
+            )
+        })
+        .build()?;
+    if let Some(path) = pdb_file {
+        pdb.save_file(path).map_err(|e| e.to_string())?;
+    }
+    Ok(Heuristic::PatternDb(Arc::new(pdb)))
+}
 
+/// Creates a state and checks that there are no errors.
+/// takes the strings passed as config, the size is either N or HxW.
+pub fn create_state(s_size: String, s_state: String) -> Result<State, ParseError> {
+    let (height, width) = create_dimensions(&s_size)?;
+    parse_grid_rect(&s_state, height, width)
+}
 
-    // Checks that the columns/lines have the right number of elements.
-    if grid.len() != size {
-        return Err("Wrong number of lines".to_string());
-    }
-    if grid.iter().any(|line| line.len() != size) {
-        return Err("Some lines does not have the specified length".to_string());
-    }
-    if zero.is_none() {
-        return Err("Please set the empty cell to zero".to_string());
+/// Reads the size of a board : N or HxW.
+pub fn create_dimensions(s_size: &str) -> Result<(usize, usize), ParseError> {
+    let dims = s_size.split('x').map(|d| d.trim().parse()).collect::<Vec<_>>();
+    match dims[..] {
+        [Ok(s)] if s > 1 => Ok((s, s)),
+        [Ok(h), Ok(w)] if h > 1 && w > 1 => Ok((h, w)),
+        _ => Err(ParseError::InvalidSize(s_size.to_string())),
     }
-    Ok((grid, zero.unwrap()))
 }
 
 
-/// Finds the result and prints it.
-fn find(base: State, weird: bool) {
-    match astar::bfs(base, true) {
-        Some(hist) => print_hist(hist),
-        None => println!("Sorry\nNo solution could be found"),
+/// Finds the result with the given algorithm within the limits and prints it.
+/// The weight is the one of the heuristic of astar, the width the one of the beam search,
+/// astar runs in parallel on more than one thread and smastar keeps the budget of states.
+fn find(
+    base: State,
+    algorithm: &str,
+    (weight, width, threads, budget): (f64, usize, usize, usize),
+    convention: Convention,
+    stats: Option<&str>,
+    limits: &Limits,
+) {
+    let mut solver: Box<dyn Solver<Direction>> = match algorithm {
+        "astar" if threads > 1 => Box::new(ParallelAStar::new(PackedState::from(&base)).threads(threads)),
+        "astar" => Box::new(AStar::new(PackedState::from(&base)).weight(weight)),
+        "smastar" => Box::new(SmaStar::new(PackedState::from(&base)).budget(budget)),
+        "beam" => Box::new(Reducer::new(base).beam(width)),
+        _ => create_solver(algorithm, &base).expect("clap checks the algorithm"),
     };
+    print_outcome(&solver.solve_within(limits), convention, stats);
 }
 
+/// Finds solutions with anytime astar within the limits, prints each of them
+/// as it is found, then the best one as a result.
+fn find_anytime(
+    base: State,
+    weight: f64,
+    convention: Convention,
+    stats: Option<&str>,
+    limits: &Limits,
+) {
+    let mut astar = AStar::new(PackedState::from(&base)).weight(weight);
+    let outcome = astar.anytime(limits, |improvement| {
+        println!(
+            "Found {} moves after {:.3} ms, at most {:.3} times the shortest : {}",
+            improvement.moves.len(),
+            improvement.elapsed.as_secs_f64() * 1000.0,
+            improvement.bound,
+            format_moves(&improvement.moves, convention)
+        )
+    });
+    print_outcome(&outcome, convention, stats);
+}
 
-/// Prints an history of moves in readable format.
-pub fn print_hist(hist: Vec<(i32, i32)>) {
-    println!("Solution in {} moves", hist.len());
-    let mut nb = 0;
-    for i in hist {
-        nb += 1;
-        if i.0 == 1 {
-            println!("{} : DOWN", nb);
-        } else if i.0 == -1 {
-            println!("{} : UP", nb);
-        } else if i.1 == 1 {
-            println!("{} : RIGHT", nb);
-        } else if i.1 == -1 {
-            println!("{} : LEFT", nb);
+/// Prints how the search ended,
+/// followed by its statistics in the given format if any.
+fn print_outcome(outcome: &Outcome<Direction>, convention: Convention, stats: Option<&str>) {
+    match *outcome {
+        Outcome::Solved(ref solution) => {
+            print_hist(solution.moves.clone(), convention);
+            if let Optimality::Bounded(bound) = solution.optimality {
+                println!("This is at most {:.3} times the shortest solution", bound);
+            }
+        }
+        Outcome::Exhausted(_) => println!("Sorry\nNo solution could be found"),
+        Outcome::LimitReached(limit, ref partial) => {
+            println!("Sorry\nThe search stopped : {}", limit);
+            print_partial(partial, convention);
         }
+        Outcome::Cancelled(ref partial) => {
+            println!("Sorry\nThe search was cancelled");
+            print_partial(partial, convention);
+        }
+    };
+    match stats {
+        Some("json") => println!("{}", outcome.stats().to_json()),
+        Some(_) => println!("{}", outcome.stats()),
+        None => {}
+    }
+}
+
+/// Prints what a search found before it stopped.
+fn print_partial(partial: &Partial<Direction>, convention: Convention) {
+    println!("No solution is shorter than {} moves", partial.lower_bound);
+    println!(
+        "Moves to the most promising grid : {}",
+        format_moves(&partial.moves, convention)
+    );
+}
+
+
+/// Prints an history of moves in readable format, in the given convention.
+pub fn print_hist(hist: Vec<Direction>, convention: Convention) {
+    println!("Solution in {} moves", hist.len());
+    println!("Moves : {}", format_moves(&hist, convention));
+    for (nb, mv) in hist.iter().enumerate() {
+        println!("{} : {}", nb + 1, mv.convert(convention));
     }
 }
 #[cfg(test)]
 mod test {
     use super::*;
+    use taquin::astar::AStar;
+    use taquin::solver::Solver;
     #[test]
     fn main() {
-        let mut state = State::new_random(4);
+        let mut state = State::new_perfect(4);
+        state.shuffle(100, 0);
         //find(state, false);
-        print_hist(astar::AStar::new(state).solve().unwrap());
+        print_hist(AStar::new(state).solve().unwrap().moves, Convention::Blank);
+    }
+    #[test]
+    fn megabytes() {
+        assert_eq!(parse_megabytes("2"), Some(2 * 1024 * 1024));
+        assert_eq!(parse_megabytes("-1"), None);
+        assert_eq!(parse_megabytes(&(usize::MAX / 1024).to_string()), None);
     }
 }
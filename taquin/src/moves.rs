@@ -0,0 +1,186 @@
+//! Sequences of moves : replaying, checking and simplifying solutions.
+
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+use direction::{format_moves, parse_moves, Convention, Direction, MoveParseError};
+use state::State;
+
+/// A sequence of moves of the empty cell.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct MoveSeq {
+    moves: Vec<Direction>,
+}
+
+/// The reasons why a sequence of moves is not a solution.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MoveError {
+    /// A move takes the empty cell out of the board.
+    Illegal {
+        /// The index of the move in the sequence.
+        index: usize,
+        /// The direction of the move.
+        direction: Direction,
+        /// The position of the empty cell before the move.
+        blank: (usize, usize),
+    },
+    /// All the moves are legal but the goal is not reached at the end.
+    NotSolved,
+}
+
+impl fmt::Display for MoveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            MoveError::Illegal {
+                index,
+                direction,
+                blank,
+            } => write!(
+                f,
+                "Move {} ({}) is illegal : the empty cell is at {}, {}",
+                index + 1,
+                direction,
+                blank.0,
+                blank.1
+            ),
+            MoveError::NotSolved => write!(f, "The moves do not reach the goal"),
+        }
+    }
+}
+
+impl Error for MoveError {}
+
+impl MoveSeq {
+    /// Creates a sequence from the given moves.
+    pub fn new(moves: Vec<Direction>) -> Self {
+        MoveSeq { moves }
+    }
+    /// Returns the moves of the sequence.
+    pub fn moves(&self) -> &[Direction] {
+        &self.moves
+    }
+    /// Returns the number of moves.
+    pub fn len(&self) -> usize {
+        self.moves.len()
+    }
+    /// Checks if there are no moves.
+    pub fn is_empty(&self) -> bool {
+        self.moves.is_empty()
+    }
+    /// Plays the moves on a copy of the state and returns it,
+    /// or the first move that is not legal.
+    pub fn apply(&self, state: &State) -> Result<State, MoveError> {
+        let mut state = state.clone();
+        for (index, &direction) in self.moves.iter().enumerate() {
+            if !state.validate(direction) {
+                return Err(MoveError::Illegal {
+                    index,
+                    direction,
+                    blank: state.white_pos(),
+                });
+            }
+            state.modify(direction);
+        }
+        Ok(state)
+    }
+    /// Returns the sequence undoing this one : the inverse moves in reverse order.
+    pub fn inverse(&self) -> MoveSeq {
+        MoveSeq::new(self.moves.iter().rev().map(|d| d.inverse()).collect())
+    }
+    /// Returns this sequence followed by the other one.
+    pub fn concat(&self, other: &MoveSeq) -> MoveSeq {
+        MoveSeq::new(self.moves.iter().chain(other.moves.iter()).cloned().collect())
+    }
+    /// Removes the moves immediately undone by the next one, until none are left.
+    /// The simplified sequence reaches the same state.
+    pub fn cancel_pairs(&self) -> MoveSeq {
+        let mut moves: Vec<Direction> = vec![];
+        for &direction in self.moves.iter() {
+            if moves.last() == Some(&direction.inverse()) {
+                moves.pop();
+            } else {
+                moves.push(direction);
+            }
+        }
+        MoveSeq::new(moves)
+    }
+    /// Writes the moves in the given convention : `RRDLU`.
+    pub fn notation(&self, convention: Convention) -> String {
+        format_moves(&self.moves, convention)
+    }
+}
+
+/// Checks that the moves are legal and reach the goal of the start state.
+pub fn verify_solution(start: &State, moves: &MoveSeq) -> Result<(), MoveError> {
+    if moves.apply(start)?.is_final() {
+        Ok(())
+    } else {
+        Err(MoveError::NotSolved)
+    }
+}
+
+impl From<Vec<Direction>> for MoveSeq {
+    fn from(moves: Vec<Direction>) -> Self {
+        MoveSeq::new(moves)
+    }
+}
+
+impl FromStr for MoveSeq {
+    type Err = MoveParseError;
+    /// Reads moves of the empty cell : `RRDLU`.
+    fn from_str(s: &str) -> Result<MoveSeq, MoveParseError> {
+        parse_moves(s, Convention::Blank).map(MoveSeq::new)
+    }
+}
+
+impl fmt::Display for MoveSeq {
+    /// Writes the moves of the empty cell : `RRDLU`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.notation(Convention::Blank))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use astar::ida_star;
+
+    #[test]
+    fn replay_and_verify() {
+        let mut start = State::new_perfect(3);
+        start.shuffle(20, 1);
+        let solution = MoveSeq::new(ida_star(start.clone()).unwrap());
+        assert_eq!(verify_solution(&start, &solution), Ok(()));
+        let solved = solution.apply(&start).unwrap();
+        assert_eq!(solution.inverse().apply(&solved), Ok(start.clone()));
+        let back_and_forth = solution.concat(&solution.inverse());
+        assert_eq!(back_and_forth.apply(&start), Ok(start.clone()));
+        assert!(back_and_forth.cancel_pairs().is_empty());
+        assert_eq!(
+            verify_solution(&start, &solution.concat(&"UD".parse().unwrap()).cancel_pairs()),
+            Ok(())
+        );
+
+        let perfect = State::new_perfect(3);
+        assert_eq!(
+            "UUU".parse::<MoveSeq>().unwrap().apply(&perfect),
+            Err(MoveError::Illegal {
+                index: 2,
+                direction: Direction::Up,
+                blank: (0, 2),
+            })
+        );
+        assert_eq!(
+            verify_solution(&perfect, &"L".parse().unwrap()),
+            Err(MoveError::NotSolved)
+        );
+    }
+
+    #[test]
+    fn cancel_pairs() {
+        let moves: MoveSeq = "RLLUDDRUR".parse().unwrap();
+        assert_eq!(moves.cancel_pairs().to_string(), "LDRUR");
+        let moves: MoveSeq = "RULDDURL".parse().unwrap();
+        assert_eq!(moves.cancel_pairs().to_string(), "RULD");
+    }
+}
@@ -0,0 +1,275 @@
+//! A compact representation of the game, cheap to clone, hash and compare.
+//!
+//! Boards of up to 16 cells are packed in a u64, 4 bits per cell.
+//! Larger boards use a byte per cell up to 256 cells, two bytes up to 65536 cells
+//! and four beyond.
+
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+use astar::{DistNode, ReversibleNode};
+use direction::Direction;
+use goal::Goal;
+use heuristic::Heuristic;
+use state::{Board, State};
+
+/// The cells of a packed state, row by row.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+enum Cells {
+    /// 4 bits per cell, the first cell in the lowest bits.
+    Nibbles(u64),
+    /// One byte per cell.
+    Bytes(Box<[u8]>),
+    /// Two bytes per cell.
+    Words(Box<[u16]>),
+    /// Four bytes per cell.
+    Wide(Box<[u32]>),
+}
+
+impl Cells {
+    /// Creates the cells of a board of the given number of cells, all empty.
+    fn new(len: usize) -> Self {
+        if len <= 16 {
+            Cells::Nibbles(0)
+        } else if len <= 1 << 8 {
+            Cells::Bytes(vec![0; len].into_boxed_slice())
+        } else if len <= 1 << 16 {
+            Cells::Words(vec![0; len].into_boxed_slice())
+        } else {
+            Cells::Wide(vec![0; len].into_boxed_slice())
+        }
+    }
+    /// Returns the number of bytes stored outside of the enum.
+    fn heap_size(&self) -> usize {
+        match *self {
+            Cells::Nibbles(_) => 0,
+            Cells::Bytes(ref bytes) => bytes.len(),
+            Cells::Words(ref words) => 2 * words.len(),
+            Cells::Wide(ref wide) => 4 * wide.len(),
+        }
+    }
+    fn get(&self, i: usize) -> usize {
+        match *self {
+            Cells::Nibbles(bits) => ((bits >> (4 * i)) & 0xf) as usize,
+            Cells::Bytes(ref bytes) => bytes[i] as usize,
+            Cells::Words(ref words) => words[i] as usize,
+            Cells::Wide(ref wide) => wide[i] as usize,
+        }
+    }
+    fn set(&mut self, i: usize, value: usize) {
+        match *self {
+            Cells::Nibbles(ref mut bits) => {
+                *bits = (*bits & !(0xf << (4 * i))) | ((value as u64) << (4 * i));
+            }
+            Cells::Bytes(ref mut bytes) => bytes[i] = value as u8,
+            Cells::Words(ref mut words) => words[i] = value as u16,
+            Cells::Wide(ref mut wide) => wide[i] = value as u32,
+        }
+    }
+}
+
+/// The state of the game, packed.
+/// Two states are equal when their cells are, whatever their heuristic and goal.
+#[derive(Clone, Debug)]
+pub struct PackedState {
+    cells: Cells,
+    /// the index of the empty cell.
+    blank: usize,
+    height: usize,
+    width: usize,
+    heuristic: Heuristic,
+    goal: Arc<Goal>,
+}
+
+impl PartialEq for PackedState {
+    fn eq(&self, other: &PackedState) -> bool {
+        self.cells == other.cells
+    }
+}
+
+impl Eq for PackedState {}
+
+impl Hash for PackedState {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.cells.hash(state);
+    }
+}
+
+impl PackedState {
+    /// Returns the position of the empty cell.
+    pub fn white_pos(&self) -> (usize, usize) {
+        (self.blank / self.width, self.blank % self.width)
+    }
+    /// Sets the estimation used for the distance to the final state.
+    pub fn set_heuristic(&mut self, heuristic: Heuristic) {
+        self.heuristic = heuristic;
+    }
+    /// Get all possible moves at the current state.
+    pub fn moves(&self) -> Vec<Direction> {
+        let (x, y) = self.white_pos();
+        let mut moves = vec![];
+        if x > 0 {
+            moves.push(Direction::Up);
+        }
+        if x < self.height - 1 {
+            moves.push(Direction::Down);
+        }
+        if y > 0 {
+            moves.push(Direction::Left);
+        }
+        if y < self.width - 1 {
+            moves.push(Direction::Right);
+        }
+        moves
+    }
+    /// Takes a move and apply it to the state.
+    pub fn modify(&mut self, mv: Direction) {
+        let mv = mv.delta();
+        let target = (self.blank as i32 + mv.0 * self.width as i32 + mv.1) as usize;
+        let value = self.cells.get(target);
+        self.cells.set(self.blank, value);
+        self.cells.set(target, 0);
+        self.blank = target;
+    }
+    /// Checks if the state has reached its goal.
+    pub fn is_final(&self) -> bool {
+        self.goal.cells().iter().enumerate().all(|(i, &value)| self.cells.get(i) == value)
+    }
+}
+
+impl<'a> From<&'a State> for PackedState {
+    fn from(state: &'a State) -> Self {
+        let cells = state.cells();
+        let mut packed = Cells::new(cells.len());
+        for (i, &value) in cells.iter().enumerate() {
+            packed.set(i, value);
+        }
+        let (x, y) = state.white_pos();
+        PackedState {
+            cells: packed,
+            blank: x * state.width + y,
+            height: state.height,
+            width: state.width,
+            heuristic: state.heuristic().clone(),
+            goal: state.shared_goal(),
+        }
+    }
+}
+
+impl<'a> From<&'a PackedState> for State {
+    fn from(packed: &'a PackedState) -> Self {
+        let table = (0..packed.height)
+            .map(|x| (0..packed.width).map(|y| packed.cell(x, y)).collect())
+            .collect();
+        let (x, y) = packed.white_pos();
+        let mut state = State::new(x, y, table).expect("a packed state has rows and columns");
+        state.set_heuristic(packed.heuristic.clone());
+        state.set_shared_goal(packed.goal.clone());
+        state
+    }
+}
+
+impl Board for PackedState {
+    fn height(&self) -> usize {
+        self.height
+    }
+    fn width(&self) -> usize {
+        self.width
+    }
+    fn cell(&self, x: usize, y: usize) -> usize {
+        self.cells.get(x * self.width + y)
+    }
+    fn goal(&self) -> &Goal {
+        &self.goal
+    }
+}
+
+impl DistNode<Direction> for PackedState {
+    fn dist_from_end(&self) -> i32 {
+        self.heuristic.estimate(self)
+    }
+
+    fn moves(&self) -> Vec<Direction> {
+        self.moves()
+    }
+
+    fn modify(&mut self, mv: &Direction) {
+        self.modify(*mv);
+    }
+    fn end(&self) -> bool {
+        self.is_final()
+    }
+    fn cost_to(&self, _target: &Self) -> i32 {
+        1
+    }
+    fn memory_size(&self) -> usize {
+        size_of::<Self>() + self.cells.heap_size()
+    }
+}
+
+impl ReversibleNode<Direction> for PackedState {
+    fn reverse_move(&self, mv: &Direction) -> Direction {
+        mv.inverse()
+    }
+    fn final_node(&self) -> Self {
+        let mut node = self.clone();
+        for (i, &value) in self.goal.cells().iter().enumerate() {
+            node.cells.set(i, value);
+        }
+        let (x, y) = self.goal.position(0);
+        node.blank = x * self.width + y;
+        node
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use astar::ida_star;
+
+    #[test]
+    fn packed_state_follows_state() {
+        for &(height, width) in [(3, 3), (4, 4), (6, 6), (2, 5), (5, 4), (17, 17)].iter() {
+            let mut state = State::new_perfect_rect(height, width);
+            let mut packed = PackedState::from(&state);
+            assert!(packed.is_final());
+            for seed in 0..50 {
+                state.shuffle(3, seed);
+                let moves = state.moves();
+                packed = PackedState::from(&state);
+                assert_eq!(packed.moves(), moves);
+                assert_eq!(State::from(&packed), state);
+                assert_eq!(packed.dist_from_end(), state.dist_from_end());
+                assert_eq!(packed.is_final(), state.is_final());
+            }
+            let mut moved = packed.clone();
+            let mv = moved.moves()[0];
+            moved.modify(mv);
+            let mut expected = State::from(&packed);
+            expected.modify(mv);
+            assert_eq!(State::from(&moved), expected);
+        }
+    }
+
+    #[test]
+    fn cells_fit_any_board() {
+        for &len in [16, 256, 257, 1 << 16, (1 << 16) + 1].iter() {
+            let mut cells = Cells::new(len);
+            cells.set(0, len - 1);
+            cells.set(len - 1, 1);
+            assert_eq!((cells.get(0), cells.get(len - 1)), (len - 1, 1));
+        }
+    }
+
+    #[test]
+    fn packed_state_solves() {
+        let mut state = State::new_perfect(4);
+        state.shuffle(30, 0);
+        let packed = PackedState::from(&state);
+        let solution = ida_star(packed).unwrap();
+        assert_eq!(solution.len(), ida_star(state.clone()).unwrap().len());
+        for mv in solution {
+            state.modify(mv);
+        }
+        assert!(state.is_final());
+    }
+}
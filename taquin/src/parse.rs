@@ -0,0 +1,397 @@
+//! Reading grids from text.
+//!
+//! Three notations are understood :
+//!
+//! * the grid notation, rows separated by colons and cells by dots : `1.2.3:4.5.6:7.0.8`
+//! * the lines notation, one row per line and cells separated by whitespace :
+//!
+//! ```text
+//! 1 2 3
+//! 4 5 6
+//! 7 0 8
+//! ```
+//!
+//! * the flat notation, the dimensions then the cells separated by commas : `3x3:1,2,3,4,5,6,7,0,8`
+//!
+//! In all notations the empty cell is 0.
+
+use std::error::Error;
+use std::fmt;
+use state::State;
+
+/// The reasons why a grid can not be read.
+/// Positions are given as (row, column), starting at 0.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParseError {
+    /// The size is not a positive integer.
+    InvalidSize(String),
+    /// A cell is not an integer.
+    InvalidInteger {
+        /// The position of the cell.
+        position: (usize, usize),
+        /// The text of the cell.
+        text: String,
+    },
+    /// The grid does not have the expected number of rows.
+    WrongRowCount {
+        /// The expected number of rows.
+        expected: usize,
+        /// The number of rows found.
+        found: usize,
+    },
+    /// A row does not have the expected number of cells.
+    WrongRowLength {
+        /// The index of the row.
+        row: usize,
+        /// The expected number of cells.
+        expected: usize,
+        /// The number of cells found.
+        found: usize,
+    },
+    /// The flat notation does not have the number of cells given by its dimensions.
+    WrongCellCount {
+        /// The expected number of cells.
+        expected: usize,
+        /// The number of cells found.
+        found: usize,
+    },
+    /// No cell is zero, so there is no empty cell.
+    MissingBlank,
+    /// A value is too big for the grid.
+    OutOfRange {
+        /// The position of the cell.
+        position: (usize, usize),
+        /// The value of the cell.
+        value: usize,
+        /// The biggest value allowed.
+        max: usize,
+    },
+    /// A value appears twice, so some other values are missing.
+    Duplicate {
+        /// The value found twice.
+        value: usize,
+        /// The position of its first occurrence.
+        first: (usize, usize),
+        /// The position of its second occurrence.
+        second: (usize, usize),
+        /// The values missing from the grid.
+        missing: Vec<usize>,
+    },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ParseError::InvalidSize(ref text) => {
+                write!(f, "Please input the size as a positive integer, not {:?}", text)
+            }
+            ParseError::InvalidInteger { position, ref text } => write!(
+                f,
+                "error at position : {}, {} cannot parse integer {:?}",
+                position.0,
+                position.1,
+                text
+            ),
+            ParseError::WrongRowCount { expected, found } => {
+                write!(f, "Wrong number of lines : {} instead of {}", found, expected)
+            }
+            ParseError::WrongRowLength {
+                row,
+                expected,
+                found,
+            } => write!(
+                f,
+                "Line {} has {} cells instead of {}",
+                row,
+                found,
+                expected
+            ),
+            ParseError::WrongCellCount { expected, found } => {
+                write!(f, "Wrong number of cells : {} instead of {}", found, expected)
+            }
+            ParseError::MissingBlank => write!(f, "Please set the empty cell to zero"),
+            ParseError::OutOfRange {
+                position,
+                value,
+                max,
+            } => write!(
+                f,
+                "error at position : {}, {} the value {} is bigger than {}",
+                position.0,
+                position.1,
+                value,
+                max
+            ),
+            ParseError::Duplicate {
+                value,
+                first,
+                second,
+                ref missing,
+            } => write!(
+                f,
+                "The value {} is at positions {}, {} and {}, {}, missing values : {:?}",
+                value,
+                first.0,
+                first.1,
+                second.0,
+                second.1,
+                missing
+            ),
+        }
+    }
+}
+
+impl Error for ParseError {}
+
+/// Reads a square state in the grid notation, checking that it has the given size.
+pub fn parse_grid(text: &str, size: usize) -> Result<State, ParseError> {
+    parse_grid_rect(text, size, size)
+}
+
+/// Reads a state in the grid notation, checking that it has the given dimensions.
+pub fn parse_grid_rect(text: &str, height: usize, width: usize) -> Result<State, ParseError> {
+    to_state(&grid_rows(text), height, width)
+}
+
+/// Splits the grid notation in rows of cells.
+fn grid_rows(text: &str) -> Vec<Vec<&str>> {
+    text.trim()
+        .split(':')
+        .map(|row| row.split('.').collect())
+        .collect()
+}
+
+/// Reads a state in the lines notation, its dimensions are the number of lines
+/// and the length of the first one.
+pub fn parse_lines(text: &str) -> Result<State, ParseError> {
+    let rows = text.lines()
+        .map(|row| row.split_whitespace().collect::<Vec<_>>())
+        .filter(|row| !row.is_empty())
+        .collect::<Vec<_>>();
+    let width = rows.first().map_or(0, |row| row.len());
+    to_state(&rows, rows.len(), width)
+}
+
+/// Reads a state in the flat notation.
+pub fn parse_flat(text: &str) -> Result<State, ParseError> {
+    let text = text.trim();
+    let (dims, cells) = match text.find(':') {
+        Some(i) => (&text[..i], &text[i + 1..]),
+        None => return Err(ParseError::InvalidSize(text.to_string())),
+    };
+    let (height, width) = match dims.split('x').map(|d| d.trim().parse()).collect::<Vec<_>>()[..] {
+        [Ok(rows), Ok(cols)] if rows > 1 && cols > 1 => (rows, cols),
+        _ => return Err(ParseError::InvalidSize(dims.to_string())),
+    };
+    let cells = cells.split(',').map(|c| c.trim()).collect::<Vec<_>>();
+    if cells.len() != height * width {
+        return Err(ParseError::WrongCellCount {
+            expected: height * width,
+            found: cells.len(),
+        });
+    }
+    let rows = cells.chunks(width).map(|row| row.to_vec()).collect::<Vec<_>>();
+    to_state(&rows, height, width)
+}
+
+/// Reads a state in any notation, guessing it from the separators used.
+pub fn parse_state(text: &str) -> Result<State, ParseError> {
+    let text = text.trim();
+    if text.contains(',') {
+        parse_flat(text)
+    } else if text.contains(char::is_whitespace) {
+        parse_lines(text)
+    } else {
+        let rows = grid_rows(text);
+        let (height, width) = (rows.len(), rows[0].len());
+        to_state(&rows, height, width)
+    }
+}
+
+/// Parses the cells of the rows and checks them to create a state of the given dimensions.
+fn to_state(rows: &[Vec<&str>], height: usize, width: usize) -> Result<State, ParseError> {
+    if height < 2 || width < 2 {
+        return Err(ParseError::InvalidSize(format!("{}x{}", height, width)));
+    }
+    let mut grid = vec![];
+    for (id_x, row) in rows.iter().enumerate() {
+        let mut line = vec![];
+        for (id_y, cell) in row.iter().enumerate() {
+            match cell.parse() {
+                Ok(val) => line.push(val),
+                Err(_) => {
+                    return Err(ParseError::InvalidInteger {
+                        position: (id_x, id_y),
+                        text: cell.to_string(),
+                    })
+                }
+            }
+        }
+        grid.push(line);
+    }
+    // Checks that the columns/lines have the right number of elements.
+    if grid.len() != height {
+        return Err(ParseError::WrongRowCount {
+            expected: height,
+            found: grid.len(),
+        });
+    }
+    if let Some((row, line)) = grid.iter().enumerate().find(|&(_, line)| line.len() != width) {
+        return Err(ParseError::WrongRowLength {
+            row,
+            expected: width,
+            found: line.len(),
+        });
+    }
+    let (x, y) = check_values(&grid)?;
+    State::new(x, y, grid)
+}
+
+/// Checks that the grid holds each value from 0 to its number of cells minus one
+/// exactly once. The rows must already have the same length.
+/// Returns the position of the empty cell.
+pub fn check_values(grid: &[Vec<usize>]) -> Result<(usize, usize), ParseError> {
+    let cells = grid.iter().map(|row| row.len()).sum::<usize>();
+    let mut found: Vec<Option<(usize, usize)>> = vec![None; cells];
+    let blank = grid.iter()
+        .enumerate()
+        .filter_map(|(x, row)| row.iter().position(|&v| v == 0).map(|y| (x, y)))
+        .next();
+    if blank.is_none() {
+        return Err(ParseError::MissingBlank);
+    }
+    for (x, row) in grid.iter().enumerate() {
+        for (y, &value) in row.iter().enumerate() {
+            if value >= cells {
+                return Err(ParseError::OutOfRange {
+                    position: (x, y),
+                    value,
+                    max: cells - 1,
+                });
+            }
+        }
+    }
+    for (x, row) in grid.iter().enumerate() {
+        for (y, &value) in row.iter().enumerate() {
+            if let Some(first) = found[value] {
+                let mut seen = vec![false; cells];
+                for &v in grid.iter().flat_map(|row| row.iter()) {
+                    seen[v] = true;
+                }
+                return Err(ParseError::Duplicate {
+                    value,
+                    first,
+                    second: (x, y),
+                    missing: (0..cells).filter(|&v| !seen[v]).collect(),
+                });
+            }
+            found[value] = Some((x, y));
+        }
+    }
+    Ok(blank.unwrap())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn notations_round_trip() {
+        for size in 2..7 {
+            let mut state = State::new_perfect(size);
+            for seed in 0..10 {
+                state.shuffle(11, seed);
+                let grid = state.to_string();
+                let lines = format!("{:#}", state);
+                let flat = state.to_flat();
+                assert_eq!(parse_grid(&grid, size), Ok(state.clone()));
+                assert_eq!(parse_lines(&lines), Ok(state.clone()));
+                assert_eq!(parse_flat(&flat), Ok(state.clone()));
+                for text in [grid, lines, flat].iter() {
+                    assert_eq!(text.parse::<State>(), Ok(state.clone()));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn rectangular_notations() {
+        for &(height, width) in [(2, 3), (3, 2), (3, 4), (4, 6)].iter() {
+            let mut state = State::new_perfect_rect(height, width);
+            state.shuffle(11, 0);
+            let grid = state.to_string();
+            assert_eq!(parse_grid_rect(&grid, height, width), Ok(state.clone()));
+            assert_eq!(format!("{:#}", state).parse(), Ok(state.clone()));
+            assert_eq!(state.to_flat().parse(), Ok(state.clone()));
+            assert_eq!(grid.parse(), Ok(state.clone()));
+            assert_eq!(
+                parse_grid_rect(&grid, width, height),
+                Err(ParseError::WrongRowCount {
+                    expected: width,
+                    found: height,
+                })
+            );
+        }
+        assert_eq!(
+            parse_state("1.2.3:4.0"),
+            Err(ParseError::WrongRowLength {
+                row: 1,
+                expected: 3,
+                found: 2,
+            })
+        );
+    }
+
+    #[test]
+    fn notations() {
+        let state = State::new(2, 1, vec![vec![1, 2, 3], vec![4, 5, 6], vec![7, 0, 8]]).unwrap();
+        assert_eq!(State::new(0, 0, vec![]), Err(ParseError::InvalidSize("0x0".to_string())));
+        assert_eq!(State::new(0, 0, vec![vec![]]), Err(ParseError::InvalidSize("1x0".to_string())));
+        assert_eq!(
+            State::new(0, 0, vec![vec![0, 1], vec![2]]),
+            Err(ParseError::WrongRowLength {
+                row: 1,
+                expected: 2,
+                found: 1,
+            })
+        );
+        assert_eq!(state.to_string(), "1.2.3:4.5.6:7.0.8");
+        assert_eq!(format!("{:#}", state), "1 2 3\n4 5 6\n7 0 8");
+        assert_eq!(state.to_flat(), "3x3:1,2,3,4,5,6,7,0,8");
+        assert_eq!(" 1 2  3\n4 5 6\n\n7 0 8\n".parse(), Ok(state.clone()));
+        assert_eq!(
+            parse_flat("3x3:1,2,3,4,5,6,7,0"),
+            Err(ParseError::WrongCellCount {
+                expected: 9,
+                found: 8,
+            })
+        );
+        assert_eq!(
+            parse_flat("3x:1,2,3,4,5,6,7,0,8"),
+            Err(ParseError::InvalidSize("3x".to_string()))
+        );
+    }
+
+    #[test]
+    fn check_values_errors() {
+        assert_eq!(check_values(&[vec![1, 2], vec![0, 3]]), Ok((1, 0)));
+        assert_eq!(check_values(&[vec![1, 2], vec![4, 3]]), Err(ParseError::MissingBlank));
+        assert_eq!(
+            check_values(&[vec![1, 2], vec![0, 4]]),
+            Err(ParseError::OutOfRange {
+                position: (1, 1),
+                value: 4,
+                max: 3,
+            })
+        );
+        assert_eq!(
+            check_values(&[vec![0, 2], vec![0, 2]]),
+            Err(ParseError::Duplicate {
+                value: 0,
+                first: (0, 0),
+                second: (1, 0),
+                missing: vec![1, 3],
+            })
+        );
+    }
+}
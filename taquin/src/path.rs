@@ -0,0 +1,135 @@
+//! Shortest paths between two given states of the same board.
+//!
+//! The target state is used as the goal of the start state, so every search
+//! and heuristic of the library applies.
+
+use std::error::Error;
+use std::fmt;
+use astar::AStar;
+use direction::Direction;
+use goal::Goal;
+use packed::PackedState;
+use solver::Solver;
+use state::State;
+
+/// The reasons why no path can exist between two states.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PathError {
+    /// The states do not have the same dimensions.
+    SizeMismatch {
+        /// The rows and columns of the start state.
+        from: (usize, usize),
+        /// The rows and columns of the target state.
+        to: (usize, usize),
+    },
+    /// The permutation between the states does not have the parity of the
+    /// distance between their empty cells, so no sequence of moves joins them.
+    ParityMismatch {
+        /// The swaps of two cells turning the start state into the target.
+        swaps: usize,
+        /// The steps between the empty cells of the states.
+        blank_distance: usize,
+    },
+}
+
+impl fmt::Display for PathError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PathError::SizeMismatch { from, to } => write!(
+                f,
+                "The start grid is {}x{} but the target grid is {}x{}",
+                from.0,
+                from.1,
+                to.0,
+                to.1
+            ),
+            PathError::ParityMismatch {
+                swaps,
+                blank_distance,
+            } => write!(
+                f,
+                "No path exists : the grids are {} swaps of two cells apart but their empty \
+                 cells are {} steps apart, and each move changes the parity of both",
+                swaps,
+                blank_distance
+            ),
+        }
+    }
+}
+
+impl Error for PathError {}
+
+/// Returns the start state with the target as its goal, keeping its heuristic,
+/// or the reason why the target can not be reached.
+pub fn path_problem(from: &State, to: &State) -> Result<State, PathError> {
+    if (from.height, from.width) != (to.height, to.width) {
+        return Err(PathError::SizeMismatch {
+            from: (from.height, from.width),
+            to: (to.height, to.width),
+        });
+    }
+    let mut start = from.clone();
+    start.set_goal(Goal::from(to));
+    if !start.is_solvable() {
+        return Err(PathError::ParityMismatch {
+            swaps: start.swaps_to_goal(),
+            blank_distance: start.blank_distance(),
+        });
+    }
+    Ok(start)
+}
+
+/// Finds an optimal sequence of moves from one state to the other with A*,
+/// guided by the heuristic of the start state.
+pub fn find_path(from: &State, to: &State) -> Result<Vec<Direction>, PathError> {
+    let start = path_problem(from, to)?;
+    Ok(AStar::new(PackedState::from(&start))
+        .solve()
+        .expect("the parity check guarantees a path")
+        .moves)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use astar::bfs;
+
+    #[test]
+    fn paths_are_optimal() {
+        for times in 5..15 {
+            let mut from = State::new_perfect(3);
+            from.shuffle(times, times as u64);
+            let mut to = from.clone();
+            to.shuffle(times + 7, 100 + times as u64);
+            let moves = find_path(&from, &to).unwrap();
+            let mut problem = from.clone();
+            problem.set_goal(Goal::from(&to));
+            assert_eq!(moves.len(), bfs(problem).unwrap().len());
+            let mut reached = from.clone();
+            for mv in moves {
+                reached.modify(mv);
+            }
+            assert_eq!(reached, to);
+        }
+    }
+
+    #[test]
+    fn path_errors() {
+        let from = State::new_perfect(3);
+        let to = "2.1.3:4.5.6:7.8.0".parse().unwrap();
+        assert_eq!(
+            find_path(&from, &to),
+            Err(PathError::ParityMismatch {
+                swaps: 1,
+                blank_distance: 0,
+            })
+        );
+        assert_eq!(
+            find_path(&from, &State::new_perfect_rect(3, 4)),
+            Err(PathError::SizeMismatch {
+                from: (3, 3),
+                to: (3, 4),
+            })
+        );
+    }
+}
@@ -0,0 +1,880 @@
+//! Additive pattern databases.
+//!
+//! A pattern is a subset of the tiles. For each placement of its tiles on the board,
+//! the pattern stores the number of moves of these tiles needed to bring them home.
+//! The cost of moving the other tiles is ignored, so the costs of disjoint patterns
+//! can be added and the sum never overestimates the real distance.
+//!
+//! Databases can be saved to and loaded from files, see *PatternDatabase::save*
+//! for the format.
+
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use goal::Goal;
+use state::{Board, State};
+
+/// The first bytes of a pattern database file.
+const MAGIC: &[u8; 8] = b"TAQPDB\0\0";
+/// The version of the file format written by this library.
+const VERSION: u32 = 1;
+
+/// The most tiles in a pattern : the table of a bigger one has more than 20! costs.
+pub const MAX_TILES: usize = 20;
+
+/// The errors that can happen when reading a pattern database or using it.
+#[derive(Debug)]
+pub enum PdbError {
+    /// The file could not be read or written.
+    Io(io::Error),
+    /// The file is not a pattern database.
+    BadMagic,
+    /// The file was written with a format this library does not know.
+    UnsupportedVersion(u32),
+    /// The content of the file does not match its checksum.
+    Corrupted {
+        /// The checksum stored in the header.
+        expected: u64,
+        /// The checksum of the content.
+        found: u64,
+    },
+    /// The content of the file is not a valid database.
+    Invalid(String),
+    /// The database was built for boards of another size.
+    SizeMismatch {
+        /// The rows and columns of the database's boards.
+        database: (usize, usize),
+        /// The rows and columns of the state's board.
+        state: (usize, usize),
+    },
+    /// The database was built for another final state.
+    GoalMismatch,
+}
+
+impl fmt::Display for PdbError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PdbError::Io(ref e) => write!(f, "pattern database io error : {}", e),
+            PdbError::BadMagic => write!(f, "not a pattern database file"),
+            PdbError::UnsupportedVersion(v) => {
+                write!(f, "unsupported pattern database version : {}", v)
+            }
+            PdbError::Corrupted { expected, found } => write!(
+                f,
+                "corrupted pattern database : checksum {:016x} instead of {:016x}",
+                found,
+                expected
+            ),
+            PdbError::Invalid(ref why) => write!(f, "invalid pattern database : {}", why),
+            PdbError::SizeMismatch { database, state } => write!(
+                f,
+                "the pattern database is for {}x{} boards, the grid is {}x{}",
+                database.0,
+                database.1,
+                state.0,
+                state.1
+            ),
+            PdbError::GoalMismatch => {
+                write!(f, "the pattern database was built for another final state")
+            }
+        }
+    }
+}
+
+impl Error for PdbError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            PdbError::Io(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for PdbError {
+    fn from(e: io::Error) -> Self {
+        PdbError::Io(e)
+    }
+}
+
+/// The progress of a build, reported after each explored depth.
+#[derive(Clone, Copy, Debug)]
+pub struct Progress {
+    /// The index of the pattern being built.
+    pub pattern: usize,
+    /// The number of patterns to build.
+    pub patterns: usize,
+    /// The depth explored so far.
+    pub depth: usize,
+    /// The number of placements reached so far.
+    pub reached: usize,
+    /// The number of placements of the pattern's tiles.
+    pub placements: usize,
+}
+
+/// The table of one pattern : a cost for each placement of its tiles.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Pattern {
+    /// The tiles of the pattern.
+    tiles: Vec<usize>,
+    /// The number of moves of the pattern's tiles, by placement index.
+    table: Vec<u8>,
+}
+
+impl Pattern {
+    /// Returns the tiles of the pattern.
+    pub fn tiles(&self) -> &[usize] {
+        &self.tiles
+    }
+    /// Returns the costs of the pattern, by placement index.
+    pub fn table(&self) -> &[u8] {
+        &self.table
+    }
+}
+
+/// A set of disjoint patterns whose costs add up to an admissible heuristic.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PatternDatabase {
+    height: usize,
+    width: usize,
+    /// The values of the cells of the final state, row by row.
+    goal: Vec<usize>,
+    patterns: Vec<Pattern>,
+    /// The pattern of each tile and its index in it, the number of patterns if it has none.
+    slots: Vec<(usize, usize)>,
+}
+
+impl PatternDatabase {
+    /// Creates the database of the given patterns, which must be disjoint.
+    fn new(height: usize, width: usize, goal: Vec<usize>, patterns: Vec<Pattern>) -> Self {
+        let mut slots = vec![(patterns.len(), 0); height * width];
+        for (owner, pattern) in patterns.iter().enumerate() {
+            for (slot, &tile) in pattern.tiles.iter().enumerate() {
+                slots[tile] = (owner, slot);
+            }
+        }
+        PatternDatabase {
+            height,
+            width,
+            goal,
+            patterns,
+            slots,
+        }
+    }
+    /// Returns the rows and columns of the boards this database applies to.
+    pub fn dimensions(&self) -> (usize, usize) {
+        (self.height, self.width)
+    }
+    /// Returns the values of the cells of the final state, row by row.
+    pub fn goal(&self) -> &[usize] {
+        &self.goal
+    }
+    /// Returns the patterns of the database.
+    pub fn patterns(&self) -> &[Pattern] {
+        &self.patterns
+    }
+    /// Checks that the database applies to the given state :
+    /// it must have been built for the same size and the same goal.
+    pub fn check(&self, state: &State) -> Result<(), PdbError> {
+        if (state.height, state.width) != (self.height, self.width) {
+            return Err(PdbError::SizeMismatch {
+                database: (self.height, self.width),
+                state: (state.height, state.width),
+            });
+        }
+        if state.goal().cells() != &self.goal[..] {
+            return Err(PdbError::GoalMismatch);
+        }
+        Ok(())
+    }
+    /// Estimates the number of moves needed to solve the state.
+    /// The state must have the size of the database.
+    pub fn estimate<B: Board>(&self, state: &B) -> i32 {
+        debug_assert_eq!((state.height(), state.width()), (self.height, self.width));
+        let cells = self.height * self.width;
+        let mut sm = 0;
+        let mut placement = [0; MAX_TILES];
+        for (index, pattern) in self.patterns.iter().enumerate() {
+            for x in 0..self.height {
+                for y in 0..self.width {
+                    let (owner, slot) = self.slots[state.cell(x, y)];
+                    if owner == index {
+                        placement[slot] = x * self.width + y;
+                    }
+                }
+            }
+            let placement = &placement[..pattern.tiles.len()];
+            sm += i32::from(pattern.table[rank(placement, cells)]);
+        }
+        sm
+    }
+
+    /// Writes the database in the following format, all integers little endian.
+    ///
+    /// | bytes | content |
+    /// |-------|---------|
+    /// | 8 | the magic `TAQPDB\0\0` |
+    /// | 4 | the version of the format |
+    /// | 4 | reserved, zero |
+    /// | 8 | the FNV-1a checksum of everything after it |
+    /// | 4 + 4 | the rows and columns of the board |
+    /// | 4 per cell | the final state, row by row |
+    /// | 4 | the number of patterns |
+    /// | 4 + 4 per tile + 8 | for each pattern : its tiles and the length of its table |
+    /// | 0 to 7 | zeros, so the tables start on a multiple of 8 |
+    /// | 1 per placement | the tables, one after the other |
+    ///
+    /// The tables are stored as they are in memory, aligned at the end of the file,
+    /// so the file can be memory mapped and shared. This library does not map it
+    /// since it does not use unsafe code, *load* reads it.
+    pub fn save<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let mut body = vec![];
+        put_u32(&mut body, self.height as u32);
+        put_u32(&mut body, self.width as u32);
+        for &value in self.goal.iter() {
+            put_u32(&mut body, value as u32);
+        }
+        put_u32(&mut body, self.patterns.len() as u32);
+        for pattern in self.patterns.iter() {
+            put_u32(&mut body, pattern.tiles.len() as u32);
+            for &tile in pattern.tiles.iter() {
+                put_u32(&mut body, tile as u32);
+            }
+            put_u64(&mut body, pattern.table.len() as u64);
+        }
+        while (body.len() + 24) % 8 != 0 {
+            body.push(0);
+        }
+        let mut hash = Fnv::new();
+        hash.write(&body);
+        for pattern in self.patterns.iter() {
+            hash.write(&pattern.table);
+        }
+        writer.write_all(MAGIC)?;
+        writer.write_all(&VERSION.to_le_bytes())?;
+        writer.write_all(&[0; 4])?;
+        writer.write_all(&hash.0.to_le_bytes())?;
+        writer.write_all(&body)?;
+        for pattern in self.patterns.iter() {
+            writer.write_all(&pattern.table)?;
+        }
+        writer.flush()
+    }
+
+    /// Reads a database written by *save*, checking its content.
+    pub fn load<R: Read>(mut reader: R) -> Result<Self, PdbError> {
+        let mut header = [0; 24];
+        reader.read_exact(&mut header)?;
+        if &header[..8] != MAGIC {
+            return Err(PdbError::BadMagic);
+        }
+        let version = u32::from_le_bytes([header[8], header[9], header[10], header[11]]);
+        if version != VERSION {
+            return Err(PdbError::UnsupportedVersion(version));
+        }
+        let mut checksum = [0; 8];
+        checksum.copy_from_slice(&header[16..]);
+        let expected = u64::from_le_bytes(checksum);
+        let mut content = vec![];
+        reader.read_to_end(&mut content)?;
+        let mut hash = Fnv::new();
+        hash.write(&content);
+        if hash.0 != expected {
+            return Err(PdbError::Corrupted {
+                expected,
+                found: hash.0,
+            });
+        }
+
+        let mut cursor = Cursor {
+            bytes: &content,
+            pos: 0,
+        };
+        let rows = cursor.u32()? as usize;
+        let cols = cursor.u32()? as usize;
+        if rows < 2 || cols < 2 {
+            return Err(PdbError::Invalid(format!("unsupported board of {}x{}", rows, cols)));
+        }
+        let cells = rows * cols;
+        let mut goal = vec![];
+        let mut seen = vec![false; cells];
+        for _ in 0..cells {
+            let value = cursor.u32()? as usize;
+            if value >= cells || seen[value] {
+                return Err(PdbError::Invalid(format!("bad value {} in the final state", value)));
+            }
+            seen[value] = true;
+            goal.push(value);
+        }
+        let count = cursor.u32()? as usize;
+        let mut shapes = vec![];
+        let mut used = vec![false; cells];
+        for _ in 0..count {
+            let k = cursor.u32()? as usize;
+            if k == 0 || k >= cells || k > MAX_TILES {
+                return Err(PdbError::Invalid(format!("pattern of {} tiles", k)));
+            }
+            let mut tiles = vec![];
+            for _ in 0..k {
+                let tile = cursor.u32()? as usize;
+                if tile == 0 || tile >= cells || used[tile] {
+                    return Err(PdbError::Invalid(format!("bad tile {} in a pattern", tile)));
+                }
+                used[tile] = true;
+                tiles.push(tile);
+            }
+            let len = cursor.u64()? as usize;
+            if len != placements(cells, k) {
+                return Err(PdbError::Invalid(format!("table of {} costs for {} tiles", len, k)));
+            }
+            shapes.push((tiles, len));
+        }
+        // the tables start at a multiple of 8 bytes from the start of the file.
+        cursor.skip((cursor.pos + 24).div_ceil(8) * 8 - 24 - cursor.pos)?;
+        let mut patterns = vec![];
+        for (tiles, len) in shapes {
+            let table = cursor.take(len)?.to_vec();
+            patterns.push(Pattern { tiles, table });
+        }
+        if cursor.pos != content.len() {
+            return Err(PdbError::Invalid("trailing bytes".to_string()));
+        }
+        Ok(PatternDatabase::new(rows, cols, goal, patterns))
+    }
+
+    /// Saves the database to the given file.
+    pub fn save_file<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        self.save(BufWriter::new(File::create(path)?))
+    }
+
+    /// Loads a database from the given file.
+    pub fn load_file<P: AsRef<Path>>(path: P) -> Result<Self, PdbError> {
+        PatternDatabase::load(BufReader::new(File::open(path)?))
+    }
+}
+
+/// Appends a little endian u32.
+fn put_u32(bytes: &mut Vec<u8>, value: u32) {
+    bytes.extend_from_slice(&value.to_le_bytes());
+}
+
+/// Appends a little endian u64.
+fn put_u64(bytes: &mut Vec<u8>, value: u64) {
+    bytes.extend_from_slice(&value.to_le_bytes());
+}
+
+/// The 64 bits FNV-1a hash.
+struct Fnv(u64);
+
+impl Fnv {
+    fn new() -> Self {
+        Fnv(0xcbf2_9ce4_8422_2325)
+    }
+    fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 ^= u64::from(b);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+}
+
+/// Reads little endian integers from bytes.
+struct Cursor<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], PdbError> {
+        let end = match self.pos.checked_add(len) {
+            Some(end) if end <= self.bytes.len() => end,
+            _ => return Err(PdbError::Invalid("truncated file".to_string())),
+        };
+        let bytes = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+    /// Skips padding bytes, which must be zeros.
+    fn skip(&mut self, len: usize) -> Result<(), PdbError> {
+        if self.take(len)?.iter().any(|&b| b != 0) {
+            return Err(PdbError::Invalid("padding bytes are not zeros".to_string()));
+        }
+        Ok(())
+    }
+    fn u32(&mut self) -> Result<u32, PdbError> {
+        let mut b = [0; 4];
+        b.copy_from_slice(self.take(4)?);
+        Ok(u32::from_le_bytes(b))
+    }
+    fn u64(&mut self) -> Result<u64, PdbError> {
+        let mut b = [0; 8];
+        b.copy_from_slice(self.take(8)?);
+        Ok(u64::from_le_bytes(b))
+    }
+}
+
+/// Returns the usual partition for the given size, if there is one.
+/// 3x3 boards use two patterns of 4 tiles, 4x4 boards the 6-6-3 partition
+/// and 5x5 boards the 6-6-6-6 partition.
+pub fn default_partition(size: usize) -> Option<Vec<Vec<usize>>> {
+    match size {
+        3 => Some(vec![vec![1, 2, 3, 4], vec![5, 6, 7, 8]]),
+        4 => Some(partition_663()),
+        5 => Some(partition_6666()),
+        _ => None,
+    }
+}
+
+/// Returns the usual partition for boards of height rows and width columns, if there is one.
+/// Rectangular boards of up to 25 cells use patterns of 6 consecutive tiles.
+pub fn default_partition_rect(height: usize, width: usize) -> Option<Vec<Vec<usize>>> {
+    if height == width {
+        default_partition(height)
+    } else if height * width <= 25 {
+        let tiles = (1..height * width).collect::<Vec<_>>();
+        Some(tiles.chunks(6).map(|chunk| chunk.to_vec()).collect())
+    } else {
+        None
+    }
+}
+
+/// The 6-6-3 partition of the 4x4 board.
+pub fn partition_663() -> Vec<Vec<usize>> {
+    vec![
+        vec![1, 5, 6, 9, 10, 13],
+        vec![7, 8, 11, 12, 14, 15],
+        vec![2, 3, 4],
+    ]
+}
+
+/// The 7-8 partition of the 4x4 board.
+/// Building the pattern of 8 tiles needs about 1.5 GB.
+pub fn partition_78() -> Vec<Vec<usize>> {
+    vec![vec![9, 10, 11, 12, 13, 14, 15], vec![1, 2, 3, 4, 5, 6, 7, 8]]
+}
+
+/// The 6-6-6-6 partition of the 5x5 board.
+/// Building each pattern needs about 500 MB.
+pub fn partition_6666() -> Vec<Vec<usize>> {
+    vec![
+        vec![1, 2, 3, 6, 7, 8],
+        vec![4, 5, 9, 10, 14, 15],
+        vec![11, 12, 16, 17, 21, 22],
+        vec![13, 18, 19, 20, 23, 24],
+    ]
+}
+
+/// A function receiving the progress of a build.
+type ProgressFn = Box<dyn FnMut(&Progress)>;
+
+/// Builds pattern databases by searching backward from the final state.
+///
+/// ```no_run
+/// use taquin::pdb::{partition_663, PatternDatabaseBuilder};
+///
+/// let pdb = PatternDatabaseBuilder::new(4)
+///     .partition(partition_663())
+///     .progress(|p| println!("pattern {} : depth {}", p.pattern, p.depth))
+///     .build()
+///     .unwrap();
+/// ```
+pub struct PatternDatabaseBuilder {
+    height: usize,
+    width: usize,
+    partition: Vec<Vec<usize>>,
+    goal: Goal,
+    progress: Option<ProgressFn>,
+}
+
+impl fmt::Debug for PatternDatabaseBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("PatternDatabaseBuilder")
+            .field("height", &self.height)
+            .field("width", &self.width)
+            .field("partition", &self.partition)
+            .field("goal", &self.goal)
+            .finish()
+    }
+}
+
+impl PatternDatabaseBuilder {
+    /// Creates a builder for square boards of the given size,
+    /// using the default partition if there is one.
+    pub fn new(size: usize) -> Self {
+        PatternDatabaseBuilder::new_rect(size, size)
+    }
+    /// Creates a builder for boards of height rows and width columns,
+    /// using the default partition if there is one and the standard goal.
+    pub fn new_rect(height: usize, width: usize) -> Self {
+        PatternDatabaseBuilder {
+            height,
+            width,
+            partition: default_partition_rect(height, width).unwrap_or_default(),
+            goal: Goal::standard(height, width),
+            progress: None,
+        }
+    }
+    /// Sets the goal the costs are computed to, it must have the dimensions of the builder.
+    pub fn goal(mut self, goal: Goal) -> Self {
+        self.goal = goal;
+        self
+    }
+    /// Sets the partition of the tiles into disjoint patterns.
+    pub fn partition(mut self, partition: Vec<Vec<usize>>) -> Self {
+        self.partition = partition;
+        self
+    }
+    /// Sets a function called after each depth explored during the build.
+    pub fn progress<F: FnMut(&Progress) + 'static>(mut self, progress: F) -> Self {
+        self.progress = Some(Box::new(progress));
+        self
+    }
+    /// Checks the partition and builds the database.
+    pub fn build(mut self) -> Result<PatternDatabase, String> {
+        let cells = self.height * self.width;
+        if (self.goal.height(), self.goal.width()) != (self.height, self.width) {
+            return Err(format!(
+                "The goal is {}x{}, not {}x{}",
+                self.goal.height(),
+                self.goal.width(),
+                self.height,
+                self.width
+            ));
+        }
+        if self.partition.is_empty() {
+            return Err(format!("No partition given for {}x{} boards", self.height, self.width));
+        }
+        if let Some(pattern) = self.partition.iter().find(|p| p.len() > MAX_TILES) {
+            return Err(format!("A pattern has {} tiles, the most is {}", pattern.len(), MAX_TILES));
+        }
+        let mut used = vec![false; cells];
+        for tile in self.partition.iter().flat_map(|p| p.iter()) {
+            if *tile == 0 || *tile >= cells {
+                return Err(format!(
+                    "Tile {} is not on a board of {}x{}",
+                    tile,
+                    self.height,
+                    self.width
+                ));
+            }
+            if used[*tile] {
+                return Err(format!("Tile {} is in several patterns", tile));
+            }
+            used[*tile] = true;
+        }
+        let goal = self.goal.cells().to_vec();
+        let patterns = self.partition.len();
+        let mut built = vec![];
+        for (index, tiles) in self.partition.iter().enumerate() {
+            let progress = &mut self.progress;
+            let dims = (self.height, self.width);
+            let table = build_table(dims, &goal, tiles, |depth, reached, placements| {
+                if let Some(ref mut report) = *progress {
+                    report(&Progress {
+                        pattern: index,
+                        patterns,
+                        depth,
+                        reached,
+                        placements,
+                    });
+                }
+            });
+            built.push(Pattern {
+                tiles: tiles.clone(),
+                table,
+            });
+        }
+        Ok(PatternDatabase::new(self.height, self.width, goal, built))
+    }
+}
+
+/// Returns the number of ways to place k tiles on n cells.
+pub fn placements(n: usize, k: usize) -> usize {
+    (n - k + 1..n + 1).product()
+}
+
+/// Returns the index of a placement, the cells of the tiles in pattern order.
+fn rank(cells_of: &[usize], n: usize) -> usize {
+    let k = cells_of.len();
+    let mut index = 0;
+    for (i, &cell) in cells_of.iter().enumerate() {
+        let before = cells_of[..i].iter().filter(|&&c| c < cell).count();
+        index += (cell - before) * placements(n - 1 - i, k - 1 - i);
+    }
+    index
+}
+
+/// Writes in *cells_of* the placement of the given index.
+fn unrank(mut index: usize, n: usize, cells_of: &mut [usize]) {
+    let k = cells_of.len();
+    let mut free = (0..n).collect::<Vec<_>>();
+    for (i, cell) in cells_of.iter_mut().enumerate() {
+        let weight = placements(n - 1 - i, k - 1 - i);
+        *cell = free.remove(index / weight);
+        index %= weight;
+    }
+}
+
+/// Returns the cells the empty cell can reach without moving a pattern tile,
+/// the smallest one first.
+/// *marks* must be all false, it is left that way.
+fn blank_region(
+    adjacent: &[Vec<usize>],
+    occupied: &[bool],
+    from: usize,
+    region: &mut Vec<usize>,
+    marks: &mut [bool],
+) {
+    region.clear();
+    region.push(from);
+    marks[from] = true;
+    let mut i = 0;
+    while i < region.len() {
+        for &next in adjacent[region[i]].iter() {
+            if !occupied[next] && !marks[next] {
+                marks[next] = true;
+                region.push(next);
+            }
+        }
+        i += 1;
+    }
+    let mut smallest = 0;
+    for i in 0..region.len() {
+        marks[region[i]] = false;
+        if region[i] < region[smallest] {
+            smallest = i;
+        }
+    }
+    region.swap(0, smallest);
+}
+
+/// Returns the cells next to the given one on a board of height rows and width columns.
+fn neighbours((height, width): (usize, usize), cell: usize) -> Vec<usize> {
+    let (x, y) = (cell / width, cell % width);
+    let mut cells = vec![];
+    if x > 0 {
+        cells.push(cell - width);
+    }
+    if x < height - 1 {
+        cells.push(cell + width);
+    }
+    if y > 0 {
+        cells.push(cell - 1);
+    }
+    if y < width - 1 {
+        cells.push(cell + 1);
+    }
+    cells
+}
+
+/// Builds the table of a pattern with a breadth first search from the final state.
+/// The nodes of the search are a placement of the tiles and the region of the
+/// empty cell, moving the empty cell inside its region costs nothing.
+fn build_table<F: FnMut(usize, usize, usize)>(
+    dims: (usize, usize),
+    goal: &[usize],
+    tiles: &[usize],
+    mut report: F,
+) -> Vec<u8> {
+    let n = dims.0 * dims.1;
+    let k = tiles.len();
+    let total = placements(n, k);
+    let mut table = vec![u8::MAX; total];
+    let mut visited = vec![0u64; (total * n).div_ceil(64)];
+    let mut visit = |node: usize| {
+        let seen = visited[node / 64] & (1 << (node % 64)) != 0;
+        visited[node / 64] |= 1 << (node % 64);
+        !seen
+    };
+
+    let adjacent = (0..n).map(|c| neighbours(dims, c)).collect::<Vec<_>>();
+    let home = |value: usize| goal.iter().position(|&v| v == value).unwrap();
+    let mut cells_of = tiles.iter().map(|&t| home(t)).collect::<Vec<_>>();
+    let mut occupied = vec![false; n];
+    let mut marks = vec![false; n];
+    let mut region = vec![];
+    let mut blanks = vec![];
+    for &c in cells_of.iter() {
+        occupied[c] = true;
+    }
+    blank_region(&adjacent, &occupied, home(0), &mut region, &mut marks);
+    let start = rank(&cells_of, n);
+    table[start] = 0;
+    visit(start * n + region[0]);
+
+    let mut layer = vec![start * n + region[0]];
+    let mut reached = 1;
+    let mut depth = 0;
+    while !layer.is_empty() {
+        report(depth, reached, total);
+        let mut next_layer = vec![];
+        for node in layer {
+            unrank(node / n, n, &mut cells_of);
+            for o in occupied.iter_mut() {
+                *o = false;
+            }
+            for &c in cells_of.iter() {
+                occupied[c] = true;
+            }
+            blank_region(&adjacent, &occupied, node % n, &mut blanks, &mut marks);
+            for &blank in blanks.iter() {
+                for &tile_cell in adjacent[blank].iter() {
+                    if !occupied[tile_cell] {
+                        continue;
+                    }
+                    // the tile moves to the empty cell.
+                    let tile = cells_of.iter().position(|&c| c == tile_cell).unwrap();
+                    cells_of[tile] = blank;
+                    occupied[tile_cell] = false;
+                    occupied[blank] = true;
+                    blank_region(&adjacent, &occupied, tile_cell, &mut region, &mut marks);
+                    let placement = rank(&cells_of, n);
+                    if visit(placement * n + region[0]) {
+                        if table[placement] == u8::MAX {
+                            table[placement] = (depth + 1) as u8;
+                            reached += 1;
+                        }
+                        next_layer.push(placement * n + region[0]);
+                    }
+                    cells_of[tile] = tile_cell;
+                    occupied[tile_cell] = true;
+                    occupied[blank] = false;
+                }
+            }
+        }
+        layer = next_layer;
+        depth += 1;
+    }
+    table
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use astar::{ida_star, DistNode};
+    use heuristic::Heuristic;
+    use std::sync::Arc;
+
+    #[test]
+    fn rank_is_a_bijection() {
+        let mut cells_of = vec![0; 3];
+        for index in 0..placements(9, 3) {
+            unrank(index, 9, &mut cells_of);
+            assert_eq!(rank(&cells_of, 9), index);
+        }
+    }
+
+    #[test]
+    fn pattern_database_is_admissible() {
+        let pdb = Arc::new(PatternDatabaseBuilder::new(3).build().unwrap());
+        assert!(pdb.patterns().iter().all(|p| p.table().iter().all(|&c| c != u8::MAX)));
+        for times in 10..30 {
+            let mut state = State::new_perfect(3);
+            state.shuffle(times, times as u64);
+            // linear conflicts give the optimal length much faster than a breadth first search.
+            let optimal = ida_star(state.clone()).unwrap().len() as i32;
+            state.set_heuristic(Heuristic::PatternDb(pdb.clone()));
+            assert!(state.dist_from_end() <= optimal);
+            assert!(state.dist_from_end() >= Heuristic::Manhattan.estimate(&state));
+            assert_eq!(ida_star(state).unwrap().len() as i32, optimal);
+        }
+    }
+
+    #[test]
+    fn rectangular_pattern_database_is_admissible() {
+        for &(height, width) in [(2, 4), (4, 2), (2, 5)].iter() {
+            let pdb = Arc::new(PatternDatabaseBuilder::new_rect(height, width).build().unwrap());
+            for times in 10..20 {
+                let mut state = State::new_perfect_rect(height, width);
+                state.shuffle(times, times as u64);
+                state.set_heuristic(Heuristic::PatternDb(pdb.clone()));
+                let optimal = ida_star(state.clone()).unwrap().len() as i32;
+                assert!(state.dist_from_end() <= optimal);
+                assert!(state.dist_from_end() >= Heuristic::Manhattan.estimate(&state));
+            }
+        }
+    }
+
+    #[test]
+    fn pattern_database_follows_the_goal() {
+        let goal = Goal::snake(3, 3);
+        let pdb = PatternDatabaseBuilder::new(3).goal(goal.clone()).build().unwrap();
+        let pdb = Arc::new(pdb);
+        for times in 10..20 {
+            let mut state = State::new_solved(goal.clone());
+            state.shuffle(times, times as u64);
+            assert!(pdb.check(&state).is_ok());
+            let optimal = ida_star(state.clone()).unwrap().len() as i32;
+            state.set_heuristic(Heuristic::PatternDb(pdb.clone()));
+            assert!(state.dist_from_end() <= optimal);
+            assert_eq!(ida_star(state).unwrap().len() as i32, optimal);
+        }
+    }
+
+    #[test]
+    fn save_and_load() {
+        let pdb = PatternDatabaseBuilder::new(3).build().unwrap();
+        let mut bytes = vec![];
+        pdb.save(&mut bytes).unwrap();
+        assert_eq!(PatternDatabase::load(&bytes[..]).unwrap(), pdb);
+
+        let last = bytes.len() - 1;
+        bytes[last] ^= 1;
+        match PatternDatabase::load(&bytes[..]) {
+            Err(PdbError::Corrupted { .. }) => {}
+            other => panic!("expected a corrupted database, got {:?}", other),
+        }
+        bytes[0] = b'X';
+        match PatternDatabase::load(&bytes[..]) {
+            Err(PdbError::BadMagic) => {}
+            other => panic!("expected a bad magic, got {:?}", other),
+        }
+    }
+
+    /// Replaces the checksum of a saved database by the one of its content.
+    fn with_checksum(mut bytes: Vec<u8>) -> Vec<u8> {
+        let mut hash = Fnv::new();
+        hash.write(&bytes[24..]);
+        bytes[16..24].copy_from_slice(&hash.0.to_le_bytes());
+        bytes
+    }
+
+    #[test]
+    fn load_rejects_bad_layouts_with_valid_checksums() {
+        // a single pattern of 5 tiles : its header ends 4 bytes before the tables.
+        let pdb = PatternDatabaseBuilder::new_rect(2, 3).build().unwrap();
+        let mut bytes = vec![];
+        pdb.save(&mut bytes).unwrap();
+        let headers = 24 + 4 + 4 + 6 * 4 + 4 + 4 + 5 * 4 + 8;
+        assert_eq!(&bytes[headers..headers + 4], &[0; 4]);
+        for &len in [headers, headers + 2, headers - 3].iter() {
+            match PatternDatabase::load(&with_checksum(bytes[..len].to_vec())[..]) {
+                Err(PdbError::Invalid(ref why)) if why == "truncated file" => {}
+                other => panic!("expected a truncated file, got {:?}", other),
+            }
+        }
+        bytes[headers + 1] = 1;
+        match PatternDatabase::load(&with_checksum(bytes)[..]) {
+            Err(PdbError::Invalid(ref why)) if why == "padding bytes are not zeros" => {}
+            other => panic!("expected non zero padding, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn check_rejects_other_boards() {
+        let pdb = PatternDatabaseBuilder::new(3).build().unwrap();
+        assert!(pdb.check(&State::new_perfect(3)).is_ok());
+        match pdb.check(&State::new_perfect(4)) {
+            Err(PdbError::SizeMismatch { database, state }) => {
+                assert_eq!(database, (3, 3));
+                assert_eq!(state, (4, 4));
+            }
+            other => panic!("expected a size mismatch, got {:?}", other),
+        }
+        match pdb.check(&State::new_solved(Goal::spiral(3, 3))) {
+            Err(PdbError::GoalMismatch) => {}
+            other => panic!("expected a goal mismatch, got {:?}", other),
+        }
+    }
+}
@@ -1,120 +1,205 @@
 //! The Reducer module : Reduces the size of the grid by filling columns and rows.
 use std::collections::{HashSet, VecDeque};
-use astar::bfs;
+use std::hash::{Hash, Hasher};
+use std::rc::Rc;
+use std::time::Instant;
+use astar::{Bfs, DistNode};
+use beam::BeamSearch;
+use direction::Direction;
+use goal::Goal;
+use heuristic::Heuristic;
+use packed::PackedState;
+use solver::{Limits, Optimality, Outcome, Partial, SearchStats, Solution, Solver};
 use state::State;
+
+/// The largest rest of the grid solved by a single beam search, in cells.
+const BEAM_REST: usize = 16;
+/// The cells of a line holding tiles of other lines.
+const OTHER: u8 = u8::MAX;
+
 #[derive(Debug)]
 /// The reducer struct, contains the informations nececary to perform the algorithm.
 pub struct Reducer {
     grid: State,
     availables: Vec<Vec<bool>>,
-    curr_x: usize,
-    curr_y: usize,
-    moves: VecDeque<(i32, i32)>,
+    moves: VecDeque<Direction>,
+    /// the moves bringing the empty cell back to its place in the goal.
+    unwind: VecDeque<Direction>,
+    /// the width of the beam searches placing the lines, if any.
+    beam: Option<usize>,
+    /// the heuristic of the beam search solving the rest of the grid, pattern
+    /// databases only when the tiles keep their names.
+    heuristic: Heuristic,
 }
 impl Reducer {
     /// Creates a new reducer from a state.
+    /// The empty cell of the goal is first moved to the last cell, then the
+    /// tiles are renamed after their position in this layout, so that the
+    /// grid is reduced towards the standard goal.
     pub fn new(state: State) -> Self {
-        let size = state.size;
+        let (height, width) = (state.height, state.width);
+        let mut layout = State::new_solved(state.goal().clone());
+        let mut unwind = VecDeque::new();
+        while layout.x < height - 1 {
+            layout.modify(Direction::Down);
+            unwind.push_front(Direction::Up);
+        }
+        while layout.y < width - 1 {
+            layout.modify(Direction::Right);
+            unwind.push_front(Direction::Left);
+        }
+        let layout = Goal::from(&layout);
+        let table = (0..height)
+            .map(|x| {
+                (0..width)
+                    .map(|y| match state.get(x, y) {
+                        0 => 0,
+                        value => {
+                            let (gx, gy) = layout.position(value);
+                            gx * width + gy + 1
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
         Reducer {
-            grid: state,
-            availables: (0..size)
-                .map(|_| (0..size).map(|_| true).collect())
+            grid: State::new(state.x, state.y, table).expect("a state has rows and columns"),
+            availables: (0..height)
+                .map(|_| (0..width).map(|_| true).collect())
                 .collect(),
-            curr_x: 0,
-            curr_y: 0,
             moves: VecDeque::new(),
+            unwind,
+            beam: None,
+            heuristic: match *state.heuristic() {
+                // the databases are built for the goal of the state, not the renamed tiles.
+                Heuristic::PatternDb(_) if *state.goal() != Goal::standard(height, width) => {
+                    Heuristic::LinearConflict
+                }
+                ref heuristic => heuristic.clone(),
+            },
         }
     }
+    /// Places each row and column with a beam search of the given width instead of
+    /// one tile at a time, where only the tiles of the line are told apart.
+    /// The last lines are solved together by a beam search guided by the heuristic
+    /// of the state. The solutions are much shorter, but take longer to find.
+    pub fn beam(mut self, width: usize) -> Self {
+        assert!(width >= 1, "the width of the beam must be at least 1");
+        self.beam = Some(width);
+        self
+    }
     /// Reduces the grid and returns the moves needed to do so.
-    pub fn reduce(&mut self) -> Option<VecDeque<(i32, i32)>> {
-        // rows down to n -2
-        let size = self.grid.size;
-        for i in 0..size - 3 {
-            self.reduce_col(i);
-            println!("finished col : {}", i);
-            self.reduce_row(i);
-        }
-        self.reduce_row(size - 3);
-        self.moves.append(
-            &mut bfs(self.grid.clone(), true)
-                .unwrap_or(vec![(0, 0)])
-                .iter()
-                .map(|x| *x)
-                .collect(),
-        );
-        Some(self.moves.clone())
+    /// The longest side is reduced first until only two rows or columns are left,
+    /// the rest is then solved by a breadth first search.
+    pub fn reduce(&mut self) -> Option<VecDeque<Direction>> {
+        self.solve().map(|solution| solution.moves.into_iter().collect())
+    }
+    /// Reduces the rows and columns until only two of them are left,
+    /// returns the first row and column of the rest of the grid.
+    fn reduce_sides(&mut self) -> (usize, usize) {
+        let (mut row, mut col) = (0, 0);
+        while self.grid.height - row >= 3 && self.grid.width - col >= 3 {
+            if self.grid.width - col > self.grid.height - row {
+                self.reduce_col(col);
+                col += 1;
+            } else {
+                self.reduce_row(row);
+                row += 1;
+            }
+        }
+        (row, col)
+    }
+    /// Returns the part of the grid starting at the given row and column,
+    /// with its tiles numbered after their final positions in this part.
+    fn rest(&self, row: usize, col: usize) -> State {
+        let (height, width) = (self.grid.height, self.grid.width);
+        let table = (row..height)
+            .map(|x| {
+                (col..width)
+                    .map(|y| match self.grid.get(x, y) {
+                        0 => 0,
+                        value => {
+                            let (gx, gy) = ((value - 1) / width, (value - 1) % width);
+                            (gx - row) * (width - col) + gy - col + 1
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+        let (x, y) = self.grid.white_pos();
+        State::new(x - row, y - col, table).expect("the rest keeps a row and a column")
     }
     /// Reduces one row by completing it.
     pub fn reduce_row(&mut self, row: usize) {
         // rows down to n -2
-        let size = self.grid.size;
-        for i in 0..size - 1 {
-            self.bring_cell((row, i), row * size + 1 + i);
+        let width = self.grid.width;
+        for i in 0..width - 1 {
+            self.bring_cell((row, i), row * width + 1 + i);
             self.availables[row][i] = false;
         }
         // then for the last cell :
-        let last = row * size + size;
-        if self.grid.search(last) == (row + 1, size - 1) &&
-            self.grid.white_pos() == (row, size - 1)
+        let last = row * width + width;
+        if self.grid.search(last) == (row + 1, width - 1) &&
+            self.grid.white_pos() == (row, width - 1)
         {
             self.forward(last);
-        } else if self.grid.search(last) != (row, size - 1) {
-            self.bring_cell((row + 1, size - 1), last);
-            self.bring_white((row + 1, size - 3), (row + 1, size - 1));
+        } else if self.grid.search(last) != (row, width - 1) {
+            self.bring_cell((row + 1, width - 1), last);
+            self.bring_white((row + 1, width - 3), (row + 1, width - 1));
             // brings the white 2 up without moving this one
             // then : left down down right up left up right
             let moves = vec![
-                (-1, 0),
-                (0, 1),
-                (0, 1),
-                (1, 0),
-                (0, -1),
-                (-1, 0),
-                (0, -1),
-                (1, 0),
+                Direction::Up,
+                Direction::Right,
+                Direction::Right,
+                Direction::Down,
+                Direction::Left,
+                Direction::Up,
+                Direction::Left,
+                Direction::Down,
             ];
             for m in moves {
                 self.moves.push_back(m);
                 self.grid.modify(m);
             }
         }
-        self.availables[row][size - 1] = false;
+        self.availables[row][width - 1] = false;
     }
     /// Reduces one column by completing it.
     pub fn reduce_col(&mut self, col: usize) {
         // rows down to n -2
-        let size = self.grid.size;
-        for i in 0..size - 1 {
-            self.bring_cell((i, col), i * size + 1 + col);
+        let (height, width) = (self.grid.height, self.grid.width);
+        for i in 0..height - 1 {
+            self.bring_cell((i, col), i * width + 1 + col);
             self.availables[i][col] = false;
         }
         // then for the last cell :
-        let last = size * size - size + 1 + col;
-        if self.grid.search(last) == (size - 1, col + 1) &&
-            self.grid.white_pos() == (size - 1, col)
+        let last = (height - 1) * width + 1 + col;
+        if self.grid.search(last) == (height - 1, col + 1) &&
+            self.grid.white_pos() == (height - 1, col)
         {
             self.forward(last);
-        } else if self.grid.search(last) != (size - 1, col) {
-            self.bring_cell((size - 1, col + 1), last);
-            self.bring_white((size - 3, col + 1), (size - 1, col + 1));
+        } else if self.grid.search(last) != (height - 1, col) {
+            self.bring_cell((height - 1, col + 1), last);
+            self.bring_white((height - 3, col + 1), (height - 1, col + 1));
             // brings the white 2 up without moving this one
             // then : left down down right up left up right
             let moves = vec![
-                (0, -1),
-                (1, 0),
-                (1, 0),
-                (0, 1),
-                (-1, 0),
-                (0, -1),
-                (-1, 0),
-                (0, 1),
+                Direction::Left,
+                Direction::Down,
+                Direction::Down,
+                Direction::Right,
+                Direction::Up,
+                Direction::Left,
+                Direction::Up,
+                Direction::Right,
             ];
             for m in moves {
                 self.moves.push_back(m);
                 self.grid.modify(m);
             }
         }
-        self.availables[size - 1][col] = false;
+        self.availables[height - 1][col] = false;
     }
     /// Brings the cell with value "value" to the desired position
     pub fn bring_cell(&mut self, target_pos: (usize, usize), value: usize) {
@@ -123,9 +208,10 @@ impl Reducer {
         // for each step :
         for i in path {
             let target = self.grid.search(value);
+            let (dx, dy) = i.delta();
             let next_cell = (
-                (target.0 as i32 + i.0) as usize,
-                (target.1 as i32 + i.1) as usize,
+                (target.0 as i32 + dx) as usize,
+                (target.1 as i32 + dy) as usize,
             );
             // brings the white to the step and avoid the cell.
             self.bring_white(next_cell, target);
@@ -147,20 +233,21 @@ impl Reducer {
         from: (usize, usize),
         target: (usize, usize),
         avoid: (usize, usize),
-    ) -> Vec<(i32, i32)> {
+    ) -> Vec<Direction> {
         let mut visited = HashSet::new();
         let mut f = VecDeque::new();
-        f.push_back((from.clone(), vec![]));
+        f.push_back((from, vec![]));
         while let Some((nouv, history)) = f.pop_front() {
             if nouv == target {
                 return history;
             } else {
-                visited.insert(nouv.clone());
+                visited.insert(nouv);
 
                 for i in self.get_legal_moves(nouv, avoid) {
-                    let mut cop = (
-                        (nouv.0 as i32 + i.0) as usize,
-                        (nouv.1 as i32 + i.1) as usize,
+                    let (dx, dy) = i.delta();
+                    let cop = (
+                        (nouv.0 as i32 + dx) as usize,
+                        (nouv.1 as i32 + dy) as usize,
                     );
                     if !visited.contains(&cop) {
                         let mut new_hist = history.clone();
@@ -177,33 +264,314 @@ impl Reducer {
     pub fn forward(&mut self, target_val: usize) {
         let empty = self.grid.white_pos();
         let target = self.grid.search(target_val);
-        let mut mv = (0, 0);
-        if empty.0 > target.0 {
-            mv = (-1, 0);
+        let mv = if empty.0 > target.0 {
+            Direction::Up
         } else if empty.0 < target.0 {
-            mv = (1, 0);
+            Direction::Down
         } else if empty.1 < target.1 {
-            mv = (0, 1);
-        } else if empty.1 > target.1 {
-            mv = (0, -1);
-        }
+            Direction::Right
+        } else {
+            Direction::Left
+        };
         self.moves.push_back(mv);
         self.grid.modify(mv);
     }
     /// Checks to see if a move is legal.
-    pub fn get_legal_moves(&self, pos: (usize, usize), avoid: (usize, usize)) -> Vec<(i32, i32)> {
-        [(0, -1), (0, 1), (1, 0), (-1, 0)]
+    pub fn get_legal_moves(&self, pos: (usize, usize), avoid: (usize, usize)) -> Vec<Direction> {
+        [Direction::Left, Direction::Right, Direction::Down, Direction::Up]
             .iter()
-            .filter(|&&(x, y)| {
-                self.legal((pos.0 + x as usize, pos.1 + y as usize), avoid)
+            .filter(|d| {
+                let (x, y) = d.delta();
+                self.legal(
+                    ((pos.0 as i32 + x) as usize, (pos.1 as i32 + y) as usize),
+                    avoid,
+                )
             })
-            .map(|&x| x)
+            .copied()
             .collect()
     }
     /// Checks to see if a move is legal.
     pub fn legal(&self, target: (usize, usize), avoid: (usize, usize)) -> bool {
-        target.0 >= 0 && target.0 < self.grid.size && target.1 >= 0 &&
-            target.1 < self.grid.size && self.availables[target.0][target.1] &&
+        target.0 < self.grid.height && target.1 < self.grid.width && self.availables[target.0][target.1] &&
             avoid != target
     }
 }
+
+impl Solver<Direction> for Reducer {
+    fn name(&self) -> &'static str {
+        match self.beam {
+            None => "reducer",
+            Some(_) => "beam",
+        }
+    }
+    /// The rows and columns are placed one by one, so the solution is rarely the shortest.
+    /// Each search is bounded by the limits on its own.
+    fn solve_within(&mut self, limits: &Limits) -> Outcome<Direction> {
+        let start = Instant::now();
+        let mut stats = SearchStats::default();
+        let (row, col) = match self.beam {
+            None => self.reduce_sides(),
+            Some(width) => match self.place_lines(width, limits, &mut stats) {
+                Ok(rest) => rest,
+                Err(outcome) => return self.unfinished(outcome, &stats),
+            },
+        };
+        // the moves of the blank are the same in the rest of the grid.
+        let rest = self.rest(row, col);
+        let outcome = match self.beam {
+            None => Bfs::new(rest).solve_within(limits),
+            Some(width) => {
+                let mut rest = PackedState::from(&rest);
+                rest.set_heuristic(match self.heuristic {
+                    // the databases are built for the whole grid.
+                    Heuristic::PatternDb(_) if (row, col) != (0, 0) => Heuristic::LinearConflict,
+                    ref heuristic => heuristic.clone(),
+                });
+                BeamSearch::new(rest).width(width).solve_within(limits)
+            }
+        };
+        match outcome {
+            Outcome::Solved(solution) => {
+                stats.add(&solution.stats);
+                self.moves.extend(solution.moves);
+                self.moves.extend(self.unwind.iter().copied());
+                let moves = self.moves.iter().copied().collect();
+                Outcome::Solved(Solution::new(moves, Optimality::Feasible, stats, start))
+            }
+            outcome => self.unfinished(outcome, &stats),
+        }
+    }
+}
+
+impl Reducer {
+    /// Places the rows and columns with beam searches until the rest of the grid
+    /// is small enough to be solved at once, returns its first row and column.
+    fn place_lines(
+        &mut self,
+        width: usize,
+        limits: &Limits,
+        stats: &mut SearchStats,
+    ) -> Result<(usize, usize), Outcome<Direction>> {
+        let (height, grid_width) = (self.grid.height, self.grid.width);
+        let (mut row, mut col) = (0, 0);
+        while height - row >= 3 &&
+            grid_width - col >= 3 &&
+            (height - row) * (grid_width - col) > BEAM_REST
+        {
+            let cells = if grid_width - col > height - row {
+                col += 1;
+                (row..height).map(|x| (x, col - 1)).collect::<Vec<_>>()
+            } else {
+                row += 1;
+                (col..grid_width).map(|y| (row - 1, y)).collect()
+            };
+            self.place_line(&cells, width, limits, stats)?;
+        }
+        Ok((row, col))
+    }
+    /// Brings the tiles of the cells home with a beam search, then locks the cells.
+    fn place_line(
+        &mut self,
+        cells: &[(usize, usize)],
+        width: usize,
+        limits: &Limits,
+        stats: &mut SearchStats,
+    ) -> Result<(), Outcome<Direction>> {
+        let line = Line::new(&self.grid, &self.availables, cells);
+        match BeamSearch::new(line).width(width).solve_within(limits) {
+            Outcome::Solved(solution) => {
+                stats.add(&solution.stats);
+                for mv in solution.moves {
+                    self.moves.push_back(mv);
+                    self.grid.modify(mv);
+                }
+                for &(x, y) in cells {
+                    self.availables[x][y] = false;
+                }
+                Ok(())
+            }
+            outcome => Err(outcome),
+        }
+    }
+    /// Returns the outcome of a search which did not solve its part of the grid,
+    /// after the moves already played and counting the work already done.
+    /// No lower bound of the solutions is known.
+    fn unfinished(&self, outcome: Outcome<Direction>, done: &SearchStats) -> Outcome<Direction> {
+        let total = |mut stats: SearchStats| {
+            stats.add(done);
+            stats
+        };
+        let placed = |partial: Partial<Direction>| Partial {
+            moves: self.moves.iter().copied().chain(partial.moves).collect(),
+            lower_bound: 0,
+            stats: total(partial.stats),
+        };
+        match outcome {
+            Outcome::Exhausted(stats) => Outcome::Exhausted(total(stats)),
+            Outcome::LimitReached(limit, partial) => Outcome::LimitReached(limit, placed(partial)),
+            Outcome::Cancelled(partial) => Outcome::Cancelled(placed(partial)),
+            solved => solved,
+        }
+    }
+}
+
+/// The cells of the grid the tiles of a line can go through, and their homes.
+#[derive(Debug)]
+struct Layout {
+    width: usize,
+    /// whether each cell is not locked yet.
+    free: Vec<bool>,
+    /// the home of each tile of the line.
+    homes: Vec<usize>,
+}
+
+/// A grid where only the tiles of a line are told apart.
+/// The beam searches placing the lines run on it : moving the other tiles around
+/// makes no new states, so the searches do not get lost among them.
+#[derive(Clone, Debug)]
+struct Line {
+    /// the index of the tile of each cell in the line, or OTHER.
+    cells: Vec<u8>,
+    /// the index of the empty cell.
+    blank: usize,
+    layout: Rc<Layout>,
+}
+
+impl Line {
+    /// Creates the line of the given cells of the reduced grid.
+    fn new(grid: &State, availables: &[Vec<bool>], line: &[(usize, usize)]) -> Self {
+        let width = grid.width;
+        let mut cells = vec![OTHER; grid.height * width];
+        for (i, &(x, y)) in line.iter().enumerate() {
+            let (tx, ty) = grid.search(x * width + y + 1);
+            cells[tx * width + ty] = i as u8;
+        }
+        Line {
+            cells,
+            blank: grid.x * width + grid.y,
+            layout: Rc::new(Layout {
+                width,
+                free: availables.iter().flatten().copied().collect(),
+                homes: line.iter().map(|&(x, y)| x * width + y).collect(),
+            }),
+        }
+    }
+    /// Returns the number of moves between two cells.
+    fn distance(&self, a: usize, b: usize) -> i32 {
+        let width = self.layout.width;
+        ((a / width) as i32 - (b / width) as i32).abs() + ((a % width) as i32 - (b % width) as i32).abs()
+    }
+}
+
+impl PartialEq for Line {
+    fn eq(&self, other: &Line) -> bool {
+        self.blank == other.blank && self.cells == other.cells
+    }
+}
+
+impl Eq for Line {}
+
+impl Hash for Line {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.blank.hash(state);
+        self.cells.hash(state);
+    }
+}
+
+impl DistNode<Direction> for Line {
+    /// Pushing a tile one cell further takes about five moves, bringing the empty
+    /// cell next to the closest tile out of place takes the rest.
+    fn dist_from_end(&self) -> i32 {
+        let mut steps = 0;
+        let mut closest = None;
+        for (i, &tile) in self.cells.iter().enumerate() {
+            if tile != OTHER && self.layout.homes[tile as usize] != i {
+                steps += self.distance(i, self.layout.homes[tile as usize]);
+                let blank = self.distance(i, self.blank) - 1;
+                closest = Some(closest.map_or(blank, |c: i32| c.min(blank)));
+            }
+        }
+        5 * steps + closest.unwrap_or(0)
+    }
+    fn moves(&self) -> Vec<Direction> {
+        let width = self.layout.width as i32;
+        let height = self.cells.len() as i32 / width;
+        let (x, y) = (self.blank as i32 / width, self.blank as i32 % width);
+        Direction::ALL
+            .iter()
+            .cloned()
+            .filter(|mv| {
+                let (dx, dy) = mv.delta();
+                let (nx, ny) = (x + dx, y + dy);
+                nx >= 0 && ny >= 0 && nx < height && ny < width &&
+                    self.layout.free[(nx * width + ny) as usize]
+            })
+            .collect()
+    }
+    fn modify(&mut self, mv: &Direction) {
+        let (dx, dy) = mv.delta();
+        let width = self.layout.width as i32;
+        let next = ((self.blank as i32 / width + dx) * width + self.blank as i32 % width + dy) as usize;
+        self.cells.swap(self.blank, next);
+        self.blank = next;
+    }
+    fn end(&self) -> bool {
+        self.cells
+            .iter()
+            .enumerate()
+            .all(|(i, &tile)| tile == OTHER || self.layout.homes[tile as usize] == i)
+    }
+    fn cost_to(&self, _target: &Self) -> i32 {
+        1
+    }
+    fn memory_size(&self) -> usize {
+        size_of::<Self>() + self.cells.len()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use moves::{verify_solution, MoveSeq};
+    use pdb::PatternDatabaseBuilder;
+    use std::sync::Arc;
+
+    #[test]
+    fn reduces_rectangular_boards() {
+        for &(height, width) in [(3, 3), (4, 4), (3, 5), (5, 3), (4, 6), (6, 4), (2, 4), (3, 2)].iter() {
+            let mut state = State::new_random_rect(height, width, 0);
+            let moves = Reducer::new(state.clone()).reduce().unwrap();
+            for mv in moves {
+                state.modify(mv);
+            }
+            assert!(state.is_final(), "{}x{} : {}", height, width, state);
+        }
+    }
+
+    #[test]
+    fn beam_searches_shorten_the_reduction() {
+        for &(height, width) in [(10, 10), (4, 7), (7, 4)].iter() {
+            let state = State::new_random_rect(height, width, 1);
+            let reduced = Reducer::new(state.clone()).solve().unwrap();
+            let solution = Reducer::new(state.clone()).beam(100).solve().unwrap();
+            let moves = MoveSeq::new(solution.moves);
+            assert_eq!(verify_solution(&state, &moves), Ok(()), "{}x{}", height, width);
+            assert!(moves.len() < reduced.moves.len(), "{}x{}", height, width);
+        }
+    }
+
+    #[test]
+    fn pattern_databases_only_rank_the_standard_goal() {
+        for goal in [Goal::standard(3, 3), Goal::spiral(3, 3)].iter() {
+            let pdb = PatternDatabaseBuilder::new(3).goal(goal.clone()).build().unwrap();
+            let mut state = State::new_solved(goal.clone());
+            state.shuffle(40, 2);
+            state.set_heuristic(Heuristic::PatternDb(Arc::new(pdb)));
+            let reducer = Reducer::new(state.clone()).beam(100);
+            let standard = *goal == Goal::standard(3, 3);
+            assert_eq!(matches!(reducer.heuristic, Heuristic::PatternDb(_)), standard);
+            let solution = Reducer::new(state.clone()).beam(100).solve().unwrap();
+            assert_eq!(verify_solution(&state, &MoveSeq::new(solution.moves)), Ok(()));
+        }
+    }
+}
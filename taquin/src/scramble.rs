@@ -0,0 +1,192 @@
+//! Reproducible scrambles : every random state comes from an explicit seed.
+//!
+//! Two kinds of scrambles are offered :
+//!
+//! * uniformly random solvable states, every arrangement that can reach the goal
+//!   being equally likely,
+//! * random walks from a state, which never undo their previous move.
+//!
+//! States of a given optimal distance from the goal are found by walks that only
+//! take moves going farther from the goal, the distances being certified by IDA*.
+
+use std::ops::RangeInclusive;
+use rand::{Rng, SeedableRng, XorShiftRng};
+use astar::ida_star;
+use direction::Direction;
+use goal::Goal;
+use heuristic::Heuristic;
+use moves::MoveSeq;
+use packed::PackedState;
+use state::State;
+
+/// The walks tried by `random_at_distance` before giving up.
+const WALKS: usize = 20;
+
+/// Creates a random number generator from a seed, the same seed giving the same numbers.
+pub fn seeded_rng(seed: u64) -> XorShiftRng {
+    // splitmix64 spreads the bits of small seeds, xorshift needs a non zero state.
+    let mut z = seed;
+    let mut next = || {
+        z = z.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut x = z;
+        x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        x ^ (x >> 31)
+    };
+    let (a, b) = (next(), next());
+    XorShiftRng::from_seed([a as u32, (a >> 32) as u32, b as u32, (b >> 32) as u32 | 1])
+}
+
+/// Returns a uniformly random state that can reach the goal, with this goal.
+/// The cells are shuffled, and two tiles are swapped if the result can not be
+/// solved : this pairs each unsolvable arrangement with a solvable one.
+pub fn random_solvable<R: Rng>(goal: &Goal, rng: &mut R) -> State {
+    let mut cells = goal.cells().to_vec();
+    rng.shuffle(&mut cells);
+    let width = goal.width();
+    let table = cells.chunks(width).map(|row| row.to_vec()).collect::<Vec<_>>();
+    let blank = cells.iter().position(|&v| v == 0).unwrap();
+    let mut state =
+        State::new(blank / width, blank % width, table).expect("a goal has rows and columns");
+    state.set_goal(goal.clone());
+    if !state.is_solvable() {
+        // the first two cells which are not the empty one.
+        let mut tiles = (0..cells.len()).filter(|&i| i != blank);
+        let (a, b) = (tiles.next().unwrap(), tiles.next().unwrap());
+        cells.swap(a, b);
+        let table = cells.chunks(width).map(|row| row.to_vec()).collect();
+        let mut swapped = State::new(state.x, state.y, table).expect("a goal has rows and columns");
+        swapped.set_shared_goal(state.shared_goal());
+        state = swapped;
+    }
+    state
+}
+
+/// Plays the given number of random moves on the state, never undoing the previous
+/// move, and returns the moves played.
+pub fn random_walk<R: Rng>(state: &mut State, length: usize, rng: &mut R) -> MoveSeq {
+    let mut played: Vec<Direction> = vec![];
+    for _ in 0..length {
+        let mut moves = state.moves();
+        if let Some(&last) = played.last() {
+            moves.retain(|&mv| mv != last.inverse());
+        }
+        let mv = *rng.choose(&moves).unwrap();
+        state.modify(mv);
+        played.push(mv);
+    }
+    MoveSeq::new(played)
+}
+
+/// Returns a random state whose optimal solution has a length in the given range,
+/// with one of its optimal solutions, or None if no walk reached these distances.
+///
+/// Each walk starts from the goal and picks a random target in the range, then takes
+/// random moves to states one step farther from the goal. The distance of each state
+/// is the length of the solution IDA* finds with the given heuristic, so the heuristic
+/// must be admissible for this goal. A walk stops early when no move goes farther.
+pub fn random_at_distance<R: Rng>(
+    goal: &Goal,
+    heuristic: &Heuristic,
+    distances: RangeInclusive<usize>,
+    rng: &mut R,
+) -> Option<(State, MoveSeq)> {
+    let (min, max) = (*distances.start(), *distances.end());
+    if min > max {
+        return None;
+    }
+    for _ in 0..WALKS {
+        let target = rng.gen_range(min, max + 1);
+        let mut state = State::new_solved(goal.clone());
+        state.set_heuristic(heuristic.clone());
+        let mut solution = MoveSeq::default();
+        while solution.len() < target {
+            let mut moves = state.moves();
+            rng.shuffle(&mut moves);
+            let farther = moves
+                .into_iter()
+                .filter_map(|mv| {
+                    let mut next = state.clone();
+                    next.modify(mv);
+                    let moves = ida_star(PackedState::from(&next))?;
+                    if moves.len() > solution.len() {
+                        Some((next, moves))
+                    } else {
+                        None
+                    }
+                })
+                .next();
+            match farther {
+                Some((next, moves)) => {
+                    state = next;
+                    solution = MoveSeq::new(moves);
+                }
+                None => break,
+            }
+        }
+        if solution.len() >= min {
+            return Some((state, solution));
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::HashMap;
+    use astar::bfs;
+    use moves::verify_solution;
+
+    #[test]
+    fn scrambles_are_reproducible() {
+        let goal = Goal::standard(3, 4);
+        let first = random_solvable(&goal, &mut seeded_rng(7));
+        assert_eq!(first, random_solvable(&goal, &mut seeded_rng(7)));
+        assert!(first != random_solvable(&goal, &mut seeded_rng(8)));
+
+        let mut walked = State::new_perfect(4);
+        let moves = random_walk(&mut walked, 60, &mut seeded_rng(3));
+        assert_eq!(moves.len(), 60);
+        assert_eq!(moves.cancel_pairs(), moves);
+        assert_eq!(moves.apply(&State::new_perfect(4)), Ok(walked.clone()));
+        let mut again = State::new_perfect(4);
+        random_walk(&mut again, 60, &mut seeded_rng(3));
+        assert_eq!(again, walked);
+    }
+
+    #[test]
+    fn random_states_are_uniform() {
+        // the 2x2 board has 12 solvable states.
+        let goal = Goal::standard(2, 2);
+        let mut rng = seeded_rng(1);
+        let mut counts = HashMap::new();
+        for _ in 0..12000 {
+            let state = random_solvable(&goal, &mut rng);
+            assert!(state.is_solvable());
+            *counts.entry(state).or_insert(0) += 1;
+        }
+        assert_eq!(counts.len(), 12);
+        assert!(counts.values().all(|&c| c > 850 && c < 1150), "{:?}", counts);
+    }
+
+    #[test]
+    fn distances_are_optimal() {
+        let goal = Goal::standard(3, 3);
+        let mut rng = seeded_rng(2);
+        for distance in (4..16).step_by(5) {
+            let (state, solution) =
+                random_at_distance(&goal, &Heuristic::Manhattan, distance..=distance, &mut rng)
+                    .unwrap();
+            assert_eq!(solution.len(), distance);
+            assert_eq!(verify_solution(&state, &solution), Ok(()));
+            assert_eq!(bfs(state).unwrap().len(), distance);
+        }
+        let (_, solution) =
+            random_at_distance(&goal, &Heuristic::LinearConflict, 12..=14, &mut rng).unwrap();
+        assert!(solution.len() >= 12 && solution.len() <= 14);
+        // no state of the 2x2 board is more than 6 moves away.
+        let small = Goal::standard(2, 2);
+        assert!(random_at_distance(&small, &Heuristic::Manhattan, 7..=7, &mut rng).is_none());
+    }
+}
@@ -0,0 +1,411 @@
+//! Memory bounded astar, after SMA* : a best first search keeping at most a given number of states.
+//!
+//! The states are kept in a tree. When the tree is full, the leaf with the highest f cost
+//! is forgotten and its cost is backed up to its parent, which generates it again once it
+//! is the most promising state. The solutions are the shortest ones as long as the path
+//! to them fits in the budget. Otherwise the branches too deep for the budget are cut, and
+//! the search stops as if it ran out of memory once they are the most promising ones,
+//! with the length no solution is shorter than.
+
+use std::cmp::Reverse;
+use std::collections::BTreeSet;
+use std::time::Instant;
+use astar::DistNode;
+use solver::{Limit, Limits, Optimality, Outcome, Partial, SearchStats, Solution, Solver};
+
+/// The states kept when no budget is given : a few hundred megabytes on 4x4 boards.
+pub const DEFAULT_BUDGET: usize = 1_000_000;
+
+/// The cost of the branches leading to no solution.
+const INFINITE: i32 = i32::MAX;
+
+/// What became of a move from a state of the tree.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Branch {
+    /// The state it leads to was never generated.
+    Unborn,
+    /// The state it leads to is kept at this index of the tree.
+    Kept(usize),
+    /// The state it leads to was forgotten, with the cost backed up from it.
+    Forgotten(i32),
+}
+
+/// A state kept in the tree.
+#[derive(Debug)]
+struct TreeNode<U, T> {
+    node: T,
+    end: bool,
+    g: i32,
+    /// The f cost of the state itself, never lower than the one of its parent.
+    own: i32,
+    /// The lowest cost of its branches, backed up to its parent.
+    f: i32,
+    depth: usize,
+    /// The index of the parent and of its branch leading here, none for the source.
+    parent: Option<(usize, usize)>,
+    moves: Vec<U>,
+    branches: Vec<Branch>,
+}
+
+/// Where a state is listed : the states with branches to generate by lowest cost then
+/// deepest, and the leaves by highest cost then shallowest, the other way around.
+type Key = (i32, Reverse<usize>, usize);
+
+/// The tree of the states kept.
+#[derive(Debug)]
+struct Tree<U, T> {
+    nodes: Vec<Option<TreeNode<U, T>>>,
+    /// The indices of the forgotten states, to reuse.
+    free: Vec<usize>,
+    len: usize,
+    open: BTreeSet<Key>,
+    leaves: BTreeSet<Key>,
+}
+
+impl<U: Clone, T: DistNode<U> + Clone + PartialEq> Tree<U, T> {
+    fn new() -> Self {
+        Tree {
+            nodes: vec![],
+            free: vec![],
+            len: 0,
+            open: BTreeSet::new(),
+            leaves: BTreeSet::new(),
+        }
+    }
+
+    fn node(&self, index: usize) -> &TreeNode<U, T> {
+        self.nodes[index].as_ref().expect("the state is kept")
+    }
+
+    /// Returns the lowest cost of the branches of the state still to generate,
+    /// the cost of the state itself for a final state.
+    fn pending(&self, index: usize) -> i32 {
+        let node = self.node(index);
+        if node.end {
+            return node.own;
+        }
+        node.branches
+            .iter()
+            .map(|branch| match *branch {
+                Branch::Unborn => node.own,
+                Branch::Forgotten(f) => f,
+                Branch::Kept(_) => INFINITE,
+            })
+            .fold(INFINITE, i32::min)
+    }
+
+    /// Returns the lowest cost of the branches of the state.
+    fn backed_up(&self, index: usize) -> i32 {
+        let node = self.node(index);
+        node.branches
+            .iter()
+            .map(|branch| match *branch {
+                Branch::Unborn => node.own,
+                Branch::Forgotten(f) => f,
+                Branch::Kept(child) => self.node(child).f,
+            })
+            .fold(INFINITE, i32::min)
+    }
+
+    /// Returns the keys of the state in the open list and in the leaves, if it is listed.
+    /// They only depend on the state itself, not on the ones it keeps.
+    fn keys(&self, index: usize) -> (Option<Key>, Option<Key>) {
+        let node = self.node(index);
+        let pending = self.pending(index);
+        let open = Some((pending, Reverse(node.depth), index)).filter(|_| pending < INFINITE);
+        let leaf = node.parent.is_some() &&
+            node.branches.iter().all(|branch| !matches!(*branch, Branch::Kept(_)));
+        (open, Some((node.f, Reverse(node.depth), index)).filter(|_| leaf))
+    }
+
+    fn unlist(&mut self, index: usize) {
+        let (open, leaf) = self.keys(index);
+        if let Some(key) = open {
+            self.open.remove(&key);
+        }
+        if let Some(key) = leaf {
+            self.leaves.remove(&key);
+        }
+    }
+
+    fn list(&mut self, index: usize) {
+        let (open, leaf) = self.keys(index);
+        if let Some(key) = open {
+            self.open.insert(key);
+        }
+        if let Some(key) = leaf {
+            self.leaves.insert(key);
+        }
+    }
+
+    /// Keeps the state in the tree and returns its index.
+    fn insert(&mut self, node: TreeNode<U, T>) -> usize {
+        let index = match self.free.pop() {
+            Some(index) => {
+                self.nodes[index] = Some(node);
+                index
+            }
+            None => {
+                self.nodes.push(Some(node));
+                self.nodes.len() - 1
+            }
+        };
+        self.len += 1;
+        self.list(index);
+        index
+    }
+
+    /// Changes a branch of the state and backs up the costs to the source.
+    fn set_branch(&mut self, index: usize, branch: usize, value: Branch) {
+        self.unlist(index);
+        self.nodes[index].as_mut().unwrap().branches[branch] = value;
+        self.list(index);
+        let mut current = Some(index);
+        while let Some(index) = current {
+            let f = self.backed_up(index);
+            if f == self.node(index).f {
+                break;
+            }
+            self.unlist(index);
+            self.nodes[index].as_mut().unwrap().f = f;
+            self.list(index);
+            current = self.node(index).parent.map(|(parent, _)| parent);
+        }
+    }
+
+    /// Forgets the leaf with the highest cost, the shallowest among them, other than
+    /// the given state. Returns false if there is no such leaf.
+    fn forget_worst(&mut self, keep: usize) -> bool {
+        let worst = self.leaves.iter().rev().map(|key| key.2).find(|&index| index != keep);
+        let index = match worst {
+            Some(index) => index,
+            None => return false,
+        };
+        self.unlist(index);
+        let node = self.nodes[index].take().unwrap();
+        self.free.push(index);
+        self.len -= 1;
+        let (parent, branch) = node.parent.unwrap();
+        self.set_branch(parent, branch, Branch::Forgotten(node.f));
+        true
+    }
+
+    /// Returns the branch of the state to generate : the first one never generated,
+    /// or else the forgotten one with the lowest cost.
+    fn next_branch(&self, index: usize) -> usize {
+        let branches = &self.node(index).branches;
+        match branches.iter().position(|branch| *branch == Branch::Unborn) {
+            Some(branch) => branch,
+            None => (0..branches.len())
+                .min_by_key(|&branch| match branches[branch] {
+                    Branch::Forgotten(f) => f,
+                    _ => INFINITE,
+                })
+                .expect("only the states with branches to generate are open"),
+        }
+    }
+
+    /// Checks if the state is the one at the index or one of its ancestors.
+    fn on_path(&self, mut index: usize, state: &T) -> bool {
+        loop {
+            let node = self.node(index);
+            if node.node == *state {
+                return true;
+            }
+            match node.parent {
+                Some((parent, _)) => index = parent,
+                None => return false,
+            }
+        }
+    }
+
+    /// Returns the moves from the source to the state at the index.
+    fn path(&self, mut index: usize) -> Vec<U> {
+        let mut moves = vec![];
+        while let Some((parent, branch)) = self.node(index).parent {
+            moves.push(self.node(parent).moves[branch].clone());
+            index = parent;
+        }
+        moves.reverse();
+        moves
+    }
+}
+
+#[derive(Debug)]
+/// The memory bounded astar struct : the source and the number of states kept at most.
+pub struct SmaStar<T> {
+    source: T,
+    budget: usize,
+}
+
+impl<T> SmaStar<T> {
+    /// Creates a memory bounded astar from the source state, with the default budget.
+    pub fn new(source: T) -> Self {
+        SmaStar {
+            source,
+            budget: DEFAULT_BUDGET,
+        }
+    }
+    /// Sets the number of states kept at once, at least 1.
+    /// The shortest solutions are found when the budget holds the path to them.
+    pub fn budget(mut self, budget: usize) -> Self {
+        assert!(budget >= 1, "the budget must hold at least the source");
+        self.budget = budget;
+        self
+    }
+}
+
+impl<U: Clone, T: DistNode<U> + Clone + PartialEq> Solver<U> for SmaStar<T> {
+    fn name(&self) -> &'static str {
+        "smastar"
+    }
+    /// Uses memory bounded astar to find the shortest way to the final state.
+    /// Each step generates one neighbour of the most promising state, forgetting the
+    /// worst leaf when the tree is full. The neighbours which do not fit, because the
+    /// tree only holds the path to them, are cut : the search stops as if the memory
+    /// limit was reached once the lowest cost of the cut ones is the lowest of all.
+    fn solve_within(&mut self, limits: &Limits) -> Outcome<U> {
+        let start = Instant::now();
+        let mut stats = SearchStats::default();
+        let size = self.source.memory_size();
+        let mut tree = Tree::new();
+        let h = self.source.dist_from_end();
+        stats.heuristic_evals += 1;
+        let moves = self.source.moves();
+        tree.insert(TreeNode {
+            end: self.source.end(),
+            node: self.source.clone(),
+            g: 0,
+            own: h,
+            f: if moves.is_empty() { INFINITE } else { h },
+            depth: 0,
+            parent: None,
+            branches: vec![Branch::Unborn; moves.len()],
+            moves,
+        });
+        // the lowest cost of the neighbours cut for lack of memory.
+        let mut cut = INFINITE;
+        let mut closest = (h, vec![]);
+        while let Some(&(pending, _, index)) = tree.open.iter().next() {
+            if pending > cut {
+                break;
+            }
+            let node = tree.node(index);
+            if node.end {
+                return Outcome::Solved(Solution::new(
+                    tree.path(index),
+                    Optimality::Optimal,
+                    stats,
+                    start,
+                ));
+            }
+            if let Some(stop) = limits.check(&stats) {
+                let partial = Partial {
+                    moves: closest.1,
+                    lower_bound: pending.min(cut),
+                    stats: SearchStats {
+                        elapsed: start.elapsed(),
+                        ..stats
+                    },
+                };
+                return Outcome::stopped(stop, partial);
+            }
+            stats.expanded += 1;
+            let branch = tree.next_branch(index);
+            let mut next = node.node.clone();
+            next.modify(&node.moves[branch]);
+            stats.generated += 1;
+            if tree.on_path(index, &next) {
+                stats.duplicates += 1;
+                tree.set_branch(index, branch, Branch::Forgotten(INFINITE));
+                continue;
+            }
+            let g = node.g + node.node.cost_to(&next);
+            let h = next.dist_from_end();
+            stats.heuristic_evals += 1;
+            // a forgotten state keeps the cost backed up from its branches.
+            let own = match node.branches[branch] {
+                Branch::Forgotten(f) => f,
+                _ => 0,
+            }
+            .max(node.own)
+            .max(g + h);
+            let depth = node.depth + 1;
+            if tree.len >= self.budget && !tree.forget_worst(index) {
+                cut = cut.min(own);
+                tree.set_branch(index, branch, Branch::Forgotten(INFINITE));
+                continue;
+            }
+            if h < closest.0 {
+                let mut moves = tree.path(index);
+                moves.push(tree.node(index).moves[branch].clone());
+                closest = (h, moves);
+            }
+            let moves = next.moves();
+            let child = tree.insert(TreeNode {
+                end: next.end(),
+                node: next,
+                g,
+                own,
+                f: if moves.is_empty() { INFINITE } else { own },
+                depth,
+                parent: Some((index, branch)),
+                branches: vec![Branch::Unborn; moves.len()],
+                moves,
+            });
+            tree.set_branch(index, branch, Branch::Kept(child));
+            stats.record(tree.open.len(), tree.len * size);
+        }
+        let stats = SearchStats {
+            elapsed: start.elapsed(),
+            ..stats
+        };
+        if cut < INFINITE {
+            let partial = Partial {
+                moves: closest.1,
+                lower_bound: cut,
+                stats,
+            };
+            Outcome::LimitReached(Limit::Memory, partial)
+        } else {
+            Outcome::Exhausted(stats)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use astar::ida_star;
+    use direction::Direction;
+    use moves::{verify_solution, MoveSeq};
+    use packed::PackedState;
+    use state::State;
+
+    #[test]
+    fn small_budgets_keep_solutions_optimal() {
+        for seed in 0..4 {
+            let mut state = State::new_perfect(3);
+            state.shuffle(40, seed);
+            let optimal = ida_star(PackedState::from(&state)).unwrap().len();
+            for &budget in [100_000, 1000, 100, optimal + 1].iter() {
+                let mut search = SmaStar::new(PackedState::from(&state)).budget(budget);
+                let solution: Solution<Direction> = search.solve().unwrap();
+                assert_eq!(solution.optimality, Optimality::Optimal);
+                assert_eq!(solution.moves.len(), optimal, "budget {}", budget);
+                assert!(solution.stats.peak_memory <= budget * size_of::<PackedState>());
+                let moves = MoveSeq::new(solution.moves);
+                assert_eq!(verify_solution(&state, &moves), Ok(()));
+            }
+            // the path to the solution does not fit.
+            let mut search = SmaStar::new(PackedState::from(&state)).budget(optimal / 2);
+            match search.solve_within(&Limits::new()) {
+                Outcome::LimitReached(Limit::Memory, partial) => {
+                    assert!(partial.lower_bound as usize <= optimal);
+                    assert!(MoveSeq::new(partial.moves).apply(&state).is_ok());
+                }
+                outcome => panic!("{:?}", outcome),
+            }
+        }
+    }
+}
@@ -0,0 +1,362 @@
+//! Solvers : every algorithm behind a common interface.
+//!
+//! Each algorithm implements `Solver` and returns its moves in a `Solution`,
+//! with what is known about them, so that they can be chosen by name.
+//!
+//! Searches can be bounded by `Limits` and stopped from another thread with a
+//! `CancelToken`, they then return what they found so far.
+
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use astar::{AStar, Bfs, Bidirectional, IdaStar, Iddfs};
+use beam::DEFAULT_WIDTH;
+use direction::Direction;
+use packed::PackedState;
+use reducer::Reducer;
+use smastar::SmaStar;
+use state::State;
+
+/// The names of the solvers, as accepted by `create_solver`.
+pub const SOLVERS: [&str; 8] =
+    ["bfs", "iddfs", "bidirectional", "astar", "idastar", "smastar", "beam", "reducer"];
+
+/// What a solver guarantees about the length of its solutions.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Optimality {
+    /// No shorter solution exists, as long as the heuristic never overestimates.
+    Optimal,
+    /// The solution is at most this many times longer than the shortest one,
+    /// as long as the heuristic never overestimates.
+    Bounded(f64),
+    /// The moves reach the goal, but shorter solutions may exist.
+    Feasible,
+}
+
+/// What a search went through before returning.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SearchStats {
+    /// The states whose moves were explored.
+    pub expanded: usize,
+    /// The states reached by a move from an expanded state.
+    pub generated: usize,
+    /// The generated states dropped because they were already known.
+    pub duplicates: usize,
+    /// The largest number of states waiting to be expanded at once.
+    pub peak_open: usize,
+    /// The largest memory used by the stored states at once, in bytes.
+    /// This is an estimate : the overhead of the collections is not counted.
+    pub peak_memory: usize,
+    /// The calls to the heuristic.
+    pub heuristic_evals: usize,
+    /// The time the search took.
+    pub elapsed: Duration,
+}
+
+impl SearchStats {
+    /// Records the states waiting to be expanded and the memory used, keeping the peaks.
+    pub fn record(&mut self, open: usize, memory: usize) {
+        self.peak_open = self.peak_open.max(open);
+        self.peak_memory = self.peak_memory.max(memory);
+    }
+    /// Adds the work of a search run after this one, keeping the highest peaks.
+    pub fn add(&mut self, other: &SearchStats) {
+        self.expanded += other.expanded;
+        self.generated += other.generated;
+        self.duplicates += other.duplicates;
+        self.record(other.peak_open, other.peak_memory);
+        self.heuristic_evals += other.heuristic_evals;
+        self.elapsed += other.elapsed;
+    }
+    /// Writes the statistics as a JSON object, the time in seconds.
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{\"expanded\":{},\"generated\":{},\"duplicates\":{},\"peak_open\":{},\
+             \"peak_memory\":{},\"heuristic_evals\":{},\"elapsed\":{}}}",
+            self.expanded,
+            self.generated,
+            self.duplicates,
+            self.peak_open,
+            self.peak_memory,
+            self.heuristic_evals,
+            self.elapsed.as_secs_f64()
+        )
+    }
+}
+
+impl fmt::Display for SearchStats {
+    /// Writes the statistics one per line.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Expanded states : {}", self.expanded)?;
+        writeln!(f, "Generated states : {}", self.generated)?;
+        writeln!(f, "Duplicates pruned : {}", self.duplicates)?;
+        writeln!(f, "Peak open list : {}", self.peak_open)?;
+        writeln!(f, "Peak memory : {} KiB (estimate)", self.peak_memory / 1024)?;
+        writeln!(f, "Heuristic evaluations : {}", self.heuristic_evals)?;
+        write!(f, "Elapsed : {:.3} ms", self.elapsed.as_secs_f64() * 1000.0)
+    }
+}
+
+/// The moves found by a solver, with what is known about them.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Solution<U> {
+    /// The moves from the start state to a final state.
+    pub moves: Vec<U>,
+    /// Whether shorter solutions may exist.
+    pub optimality: Optimality,
+    /// The work done by the search, and the time it took.
+    pub stats: SearchStats,
+}
+
+impl<U> Solution<U> {
+    /// Creates the solution of a search started at the given instant.
+    pub fn new(moves: Vec<U>, optimality: Optimality, mut stats: SearchStats, start: Instant) -> Self {
+        stats.elapsed = start.elapsed();
+        Solution {
+            moves,
+            optimality,
+            stats,
+        }
+    }
+}
+
+/// A resource a search ran out of.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Limit {
+    /// The maximum number of expanded states was reached.
+    Nodes,
+    /// The estimated memory of the stored states reached the maximum.
+    Memory,
+    /// The deadline has passed.
+    Deadline,
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            Limit::Nodes => "the maximum number of expanded states was reached",
+            Limit::Memory => "the maximum memory was reached",
+            Limit::Deadline => "the time is up",
+        })
+    }
+}
+
+/// A flag shared with running searches, to stop them from another thread.
+#[derive(Clone, Debug, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    /// Creates a token which is not cancelled.
+    pub fn new() -> Self {
+        CancelToken::default()
+    }
+    /// Asks the searches holding this token to stop.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+    /// Checks if the searches holding this token were asked to stop.
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Why a search stopped before its end.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stop {
+    /// A limit was reached.
+    Limit(Limit),
+    /// The token of the search was cancelled.
+    Cancelled,
+}
+
+/// The resources a search may use, and the token that stops it.
+/// There are no limits by default.
+#[derive(Clone, Debug, Default)]
+pub struct Limits {
+    /// The maximum number of states to expand.
+    pub max_nodes: Option<usize>,
+    /// The maximum memory of the stored states, in bytes, as estimated in the statistics.
+    pub max_memory: Option<usize>,
+    /// The instant the search must stop at.
+    pub deadline: Option<Instant>,
+    /// The token stopping the search when cancelled.
+    pub cancel: CancelToken,
+}
+
+impl Limits {
+    /// Creates limits that never stop a search.
+    pub fn new() -> Self {
+        Limits::default()
+    }
+    /// Stops the search after this number of expanded states.
+    pub fn max_nodes(mut self, nodes: usize) -> Self {
+        self.max_nodes = Some(nodes);
+        self
+    }
+    /// Stops the search once the stored states take this many bytes.
+    pub fn max_memory(mut self, bytes: usize) -> Self {
+        self.max_memory = Some(bytes);
+        self
+    }
+    /// Stops the search once this much time has passed from now.
+    pub fn timeout(mut self, duration: Duration) -> Self {
+        self.deadline = Some(Instant::now() + duration);
+        self
+    }
+    /// Stops the search when the token is cancelled.
+    pub fn cancel_token(mut self, token: CancelToken) -> Self {
+        self.cancel = token;
+        self
+    }
+    /// Returns why a search with these statistics must stop, if it must.
+    pub fn check(&self, stats: &SearchStats) -> Option<Stop> {
+        if self.cancel.is_cancelled() {
+            Some(Stop::Cancelled)
+        } else if self.max_nodes.is_some_and(|max| stats.expanded >= max) {
+            Some(Stop::Limit(Limit::Nodes))
+        } else if self.max_memory.is_some_and(|max| stats.peak_memory >= max) {
+            Some(Stop::Limit(Limit::Memory))
+        } else if self.deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            Some(Stop::Limit(Limit::Deadline))
+        } else {
+            None
+        }
+    }
+}
+
+/// What a search knows when it stops before its end.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Partial<U> {
+    /// The moves to the most promising state reached : the closest to a final
+    /// state according to the heuristic, or the deepest for the blind searches.
+    pub moves: Vec<U>,
+    /// No solution is shorter than this.
+    pub lower_bound: i32,
+    /// The work done by the search, and the time it took.
+    pub stats: SearchStats,
+}
+
+/// How a search ended.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Outcome<U> {
+    /// A solution was found.
+    Solved(Solution<U>),
+    /// Every reachable state was explored without finding a final one.
+    Exhausted(SearchStats),
+    /// The search ran out of a resource.
+    LimitReached(Limit, Partial<U>),
+    /// The search was cancelled.
+    Cancelled(Partial<U>),
+}
+
+impl<U> Outcome<U> {
+    /// Creates the outcome of a search stopped for the given reason.
+    pub fn stopped(stop: Stop, partial: Partial<U>) -> Self {
+        match stop {
+            Stop::Limit(limit) => Outcome::LimitReached(limit, partial),
+            Stop::Cancelled => Outcome::Cancelled(partial),
+        }
+    }
+    /// Returns the solution, if one was found.
+    pub fn solution(self) -> Option<Solution<U>> {
+        match self {
+            Outcome::Solved(solution) => Some(solution),
+            _ => None,
+        }
+    }
+    /// Returns the statistics of the search.
+    pub fn stats(&self) -> &SearchStats {
+        match *self {
+            Outcome::Solved(ref solution) => &solution.stats,
+            Outcome::Exhausted(ref stats) => stats,
+            Outcome::LimitReached(_, ref partial) | Outcome::Cancelled(ref partial) => {
+                &partial.stats
+            }
+        }
+    }
+}
+
+/// An algorithm searching the moves from its start state to a final state.
+pub trait Solver<U> {
+    /// Returns the name the solver is chosen by.
+    fn name(&self) -> &'static str;
+    /// Finds the moves to a final state without exceeding the limits.
+    fn solve_within(&mut self, limits: &Limits) -> Outcome<U>;
+    /// Finds the moves to a final state, or None if there are none.
+    fn solve(&mut self) -> Option<Solution<U>> {
+        self.solve_within(&Limits::new()).solution()
+    }
+}
+
+/// Creates the solver with the given name for the state, if any.
+/// The searches run on the packed representation of the state.
+pub fn create_solver(name: &str, state: &State) -> Option<Box<dyn Solver<Direction>>> {
+    match name {
+        "bfs" => Some(Box::new(Bfs::new(PackedState::from(state)))),
+        "iddfs" => Some(Box::new(Iddfs::new(PackedState::from(state)))),
+        "bidirectional" => Some(Box::new(Bidirectional::new(PackedState::from(state)))),
+        "astar" => Some(Box::new(AStar::new(PackedState::from(state)))),
+        "idastar" => Some(Box::new(IdaStar::new(PackedState::from(state)))),
+        "smastar" => Some(Box::new(SmaStar::new(PackedState::from(state)))),
+        "beam" => Some(Box::new(Reducer::new(state.clone()).beam(DEFAULT_WIDTH))),
+        "reducer" => Some(Box::new(Reducer::new(state.clone()))),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use moves::{verify_solution, MoveSeq};
+
+    #[test]
+    fn solvers_are_interchangeable() {
+        let mut state = State::new_perfect(3);
+        state.shuffle(30, 4);
+        let mut optimal = None;
+        for name in SOLVERS.iter() {
+            let mut solver = create_solver(name, &state).unwrap();
+            assert_eq!(solver.name(), *name);
+            let solution = solver.solve().unwrap();
+            let moves = MoveSeq::new(solution.moves.clone());
+            assert_eq!(verify_solution(&state, &moves), Ok(()));
+            assert!(solution.stats.expanded > 0);
+            if solution.optimality == Optimality::Optimal {
+                assert_eq!(*optimal.get_or_insert(moves.len()), moves.len());
+            }
+        }
+        assert!(create_solver("dijkstra", &state).is_none());
+    }
+
+    #[test]
+    fn limits_stop_every_search() {
+        let mut state = State::new_perfect(3);
+        state.shuffle(16, 5);
+        let optimal = create_solver("idastar", &state).unwrap().solve().unwrap().moves.len();
+        for name in ["bfs", "iddfs", "bidirectional", "astar", "idastar", "smastar", "beam"].iter() {
+            let mut solver = create_solver(name, &state).unwrap();
+            match solver.solve_within(&Limits::new().max_nodes(10)) {
+                Outcome::LimitReached(Limit::Nodes, partial) => {
+                    assert_eq!(partial.stats.expanded, 10);
+                    assert!(partial.lower_bound as usize <= optimal);
+                    assert!(MoveSeq::new(partial.moves).apply(&state).is_ok());
+                }
+                outcome => panic!("{} : {:?}", name, outcome),
+            }
+            let limits = Limits::new().timeout(Duration::from_secs(0));
+            let outcome = solver.solve_within(&limits);
+            assert!(matches!(outcome, Outcome::LimitReached(Limit::Deadline, _)), "{}", name);
+            let outcome = solver.solve_within(&Limits::new().max_memory(1));
+            assert!(matches!(outcome, Outcome::LimitReached(Limit::Memory, _)), "{}", name);
+            let token = CancelToken::new();
+            token.cancel();
+            let outcome = solver.solve_within(&Limits::new().cancel_token(token));
+            assert!(matches!(outcome, Outcome::Cancelled(_)), "{}", name);
+            assert!(solver.solve_within(&Limits::new().max_nodes(1_000_000)).solution().is_some());
+        }
+        let unsolvable = "2.1:3.0".parse().unwrap();
+        let outcome = create_solver("bfs", &unsolvable).unwrap().solve_within(&Limits::new());
+        assert!(matches!(outcome, Outcome::Exhausted(_)));
+    }
+}
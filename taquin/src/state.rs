@@ -1,15 +1,33 @@
 //! The state containing the Fifteen puzzle.
 
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+use std::sync::Arc;
+use astar::{DistNode, ReversibleNode};
+use direction::Direction;
+use goal::Goal;
+use parse::{parse_state, ParseError};
+use scramble::{random_solvable, random_walk, seeded_rng};
+use heuristic::Heuristic;
 
-use time::PreciseTime;
-use std::collections::{HashSet, VecDeque, HashMap};
-use rand::XorShiftRng;
-use rand::Rng;
-use astar::DistNode;
+/// A board of tiles that can be read cell by cell.
+/// Lets the heuristics work on any representation of the game.
+pub trait Board {
+    /// Returns the number of rows.
+    fn height(&self) -> usize;
+    /// Returns the number of columns.
+    fn width(&self) -> usize;
+    /// Returns the value of the cell at row x and column y, 0 for the empty cell.
+    fn cell(&self, x: usize, y: usize) -> usize;
+    /// Returns the arrangement the board has to reach.
+    fn goal(&self) -> &Goal;
+}
 
-#[derive(Hash, Clone, PartialEq, Eq, Debug)]
+#[derive(Clone, Debug)]
 
 /// The struct representing the state of the game
+/// Two states are equal when their cells are, whatever their heuristic and goal.
 ///
 pub struct State {
     /// the x position of the empty cell
@@ -18,99 +36,188 @@ pub struct State {
     pub y: usize,
     /// the array of cells.
     table: Vec<Vec<usize>>,
-    /// the array's size.
-    pub size: usize,
+    /// the number of rows.
+    pub height: usize,
+    /// the number of columns.
+    pub width: usize,
+    /// the estimation used for the distance to the final state.
+    heuristic: Heuristic,
+    /// the arrangement to reach.
+    goal: Arc<Goal>,
+}
+
+impl PartialEq for State {
+    fn eq(&self, other: &State) -> bool {
+        self.x == other.x && self.y == other.y && self.table == other.table
+    }
+}
+
+impl Eq for State {}
+
+impl Hash for State {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.x.hash(state);
+        self.y.hash(state);
+        self.table.hash(state);
+    }
 }
 
 
 impl State {
     /// Creates a new state with the given positions.
-    pub fn new(x: usize, y: usize, table: Vec<Vec<usize>>, size: usize) -> Self {
-        State {
-            x: x,
-            y: y,
-            table: table,
-            size: size,
+    /// The dimensions of the board are the ones of the table, which must have a row
+    /// and a column at least, and rows of the same length. The goal is the standard one.
+    pub fn new(x: usize, y: usize, table: Vec<Vec<usize>>) -> Result<Self, ParseError> {
+        let (height, width) = (table.len(), table.first().map_or(0, Vec::len));
+        if height == 0 || width == 0 {
+            return Err(ParseError::InvalidSize(format!("{}x{}", height, width)));
         }
+        if let Some((row, line)) = table.iter().enumerate().find(|&(_, line)| line.len() != width) {
+            return Err(ParseError::WrongRowLength {
+                row,
+                expected: width,
+                found: line.len(),
+            });
+        }
+        Ok(State {
+            x,
+            y,
+            height,
+            width,
+            table,
+            heuristic: Heuristic::LinearConflict,
+            goal: Arc::new(Goal::standard(height, width)),
+        })
     }
-    /// Creates a new state with the given positions.
-    pub fn new_random(size: usize) -> Self {
-        let mut state = State::new_perfect(size);
-        state.shuffle(100);
-        state
+    /// Creates a uniformly random solvable square state from the seed.
+    pub fn new_random(size: usize, seed: u64) -> Self {
+        State::new_random_rect(size, size, seed)
+    }
+    /// Creates a uniformly random solvable state of height rows and width columns
+    /// from the seed.
+    pub fn new_random_rect(height: usize, width: usize, seed: u64) -> Self {
+        random_solvable(&Goal::standard(height, width), &mut seeded_rng(seed))
     }
-    /// Creates a new completed state
+    /// Creates a new completed square state
     pub fn new_perfect(size: usize) -> Self {
-        let mut table = vec![];
-        let mut val = 0;
-        for i in 0..size {
-            table.push(vec![]);
-            for j in 0..size {
-                val += 1;
-                table[i].push(val);
-                if val == size * size {
-                    table[i][j] = 0;
-                }
-            }
-        }
-        State {
-            x: size - 1,
-            y: size - 1,
-            table: table,
-            size: size,
-        }
+        State::new_perfect_rect(size, size)
+    }
+    /// Creates a new completed state of height rows and width columns.
+    pub fn new_perfect_rect(height: usize, width: usize) -> Self {
+        State::new_solved(Goal::standard(height, width))
+    }
+    /// Creates the state where the given goal is reached.
+    pub fn new_solved(goal: Goal) -> Self {
+        let table = goal.cells().chunks(goal.width()).map(|row| row.to_vec()).collect();
+        let (x, y) = goal.position(0);
+        let mut state = State::new(x, y, table).expect("a goal has rows and columns");
+        state.set_goal(goal);
+        state
+    }
+    /// Sets the estimation used for the distance to the final state.
+    pub fn set_heuristic(&mut self, heuristic: Heuristic) {
+        self.heuristic = heuristic;
+    }
+    /// Returns the estimation used for the distance to the final state.
+    pub fn heuristic(&self) -> &Heuristic {
+        &self.heuristic
+    }
+    /// Sets the arrangement to reach, it must have the dimensions of the state.
+    pub fn set_goal(&mut self, goal: Goal) {
+        self.set_shared_goal(Arc::new(goal));
+    }
+    /// Returns the arrangement to reach.
+    pub fn goal(&self) -> &Goal {
+        &self.goal
+    }
+    /// Returns the arrangement to reach, shared with the clones of the state.
+    pub fn shared_goal(&self) -> Arc<Goal> {
+        self.goal.clone()
+    }
+    /// Sets an arrangement to reach shared with other states.
+    pub fn set_shared_goal(&mut self, goal: Arc<Goal>) {
+        assert_eq!((goal.height(), goal.width()), (self.height, self.width));
+        self.goal = goal;
+    }
+    /// Returns the value of the cell at row x and column y, 0 for the empty cell.
+    pub fn get(&self, x: usize, y: usize) -> usize {
+        self.table[x][y]
+    }
+    /// Returns the values of the cells, row by row.
+    pub fn cells(&self) -> Vec<usize> {
+        self.table.iter().flat_map(|row| row.iter().cloned()).collect()
     }
     /// Search for the given value in the table and returns its coordinates.
     pub fn search(&self, target: usize) -> (usize, usize) {
-        for (id_x, i) in (0..self.size).enumerate() {
-            for (id_y, j) in (0..self.size).enumerate() {
+        for i in 0..self.height {
+            for j in 0..self.width {
                 if self.table[i][j] == target {
                     return (i, j);
                 }
             }
         }
-        panic!(format!("error, coult not find : {}", target))
+        panic!("error, coult not find : {}", target)
     }
     /// Returns a tuple containing the position of the white cell
     pub fn white_pos(&self) -> (usize, usize) {
         (self.x, self.y)
     }
 
-    /// Checks if the state is complete.
-    pub fn is_final(&self, from_start: bool) -> bool {
-        let mut val = 0;
-        for i in 0..self.size {
-            for j in 0..self.size {
-                if !from_start {
-                    val += 1;
-                    if val == self.size * self.size {
-                        return true;
-                    }
-                }
-                if self.table[i][j] != val {
-                    return false;
-                }
-                if from_start {
-                    val += 1;
-                }
+    /// Checks if the state has reached its goal.
+    pub fn is_final(&self) -> bool {
+        self.goal.is_reached(self)
+    }
+    /// Returns the state in the flat notation : `3x3:1,2,3,4,5,6,7,0,8`.
+    pub fn to_flat(&self) -> String {
+        let cells = self.cells().iter().map(|v| v.to_string()).collect::<Vec<_>>();
+        format!("{}x{}:{}", self.height, self.width, cells.join(","))
+    }
+    /// Counts the swaps of two cells, the empty one included, needed to turn
+    /// this state into its goal.
+    pub fn swaps_to_goal(&self) -> usize {
+        let cells = self.cells();
+        let mut seen = vec![false; cells.len()];
+        let mut cycles = 0;
+        for start in 0..cells.len() {
+            let mut cell = start;
+            if !seen[cell] {
+                cycles += 1;
+            }
+            while !seen[cell] {
+                seen[cell] = true;
+                let (x, y) = self.goal.position(cells[cell]);
+                cell = x * self.width + y;
             }
         }
-        return true;
+        cells.len() - cycles
+    }
+    /// Returns the number of steps from the empty cell to its final position.
+    pub fn blank_distance(&self) -> usize {
+        let (x, y) = self.goal.position(0);
+        self.x.abs_diff(x) + self.y.abs_diff(y)
+    }
+    /// Checks if the goal can be reached from this state.
+    /// Each move swaps the empty cell with a tile and moves the empty cell one step,
+    /// so the parity of the swaps to the goal must be the one of the distance from the
+    /// empty cell to its final position. For the standard goal, this is the usual rule
+    /// on the inversions and the row of the empty cell.
+    pub fn is_solvable(&self) -> bool {
+        (self.swaps_to_goal() + self.blank_distance()).is_multiple_of(2)
     }
     /// Get all possible moves at the current state.
-    pub fn moves(&self) -> Vec<(i32, i32)> {
+    pub fn moves(&self) -> Vec<Direction> {
         let mut moves = vec![];
         if self.x > 0 {
-            moves.push((-1, 0));
+            moves.push(Direction::Up);
         }
-        if self.x < self.size - 1 {
-            moves.push((1, 0));
+        if self.x < self.height - 1 {
+            moves.push(Direction::Down);
         }
         if self.y > 0 {
-            moves.push((0, -1));
+            moves.push(Direction::Left);
         }
-        if self.y < self.size - 1 {
-            moves.push((0, 1));
+        if self.y < self.width - 1 {
+            moves.push(Direction::Right);
         }
         moves
     }
@@ -122,11 +229,14 @@ impl State {
         }
     }
     /// Takes a move and checks if it is a legal move.
-    pub fn validate(&mut self, mv: (i32, i32)) -> bool {
+    pub fn validate(&self, mv: Direction) -> bool {
         self.moves().contains(&mv)
     }
     /// Takes a move and apply it to the state.
-    pub fn modify(&mut self, mv: (i32, i32)) {
+    /// The move must be legal, see *validate* and the *moves* module for checked moves.
+    pub fn modify(&mut self, mv: Direction) {
+        debug_assert!(self.validate(mv), "illegal move {} from {:?}", mv, self.white_pos());
+        let mv = mv.delta();
         let (new_x, new_y) = (
             (self.x as i32 + mv.0) as usize,
             (self.y as i32 + mv.1) as usize,
@@ -136,62 +246,137 @@ impl State {
         self.y = new_y;
         self.table[self.x][self.y] = 0;
     }
-    /// Shuffles the state by playing a certain number of random moves.
-    pub fn shuffle(&mut self, times: usize) {
-        let mut my_rand = XorShiftRng::new_unseeded();
-        for _ in 0..times {
-            let mov = self.moves();
-            let mv = mov.as_slice();
-            self.modify(my_rand.choose(mv).unwrap().clone());
-        }
+    /// Shuffles the state by playing a certain number of random moves chosen from
+    /// the seed, without undoing the previous move.
+    pub fn shuffle(&mut self, times: usize, seed: u64) {
+        random_walk(self, times, &mut seeded_rng(seed));
     }
     /// Returns the bottom left square composed of 3x3 grid.
     pub fn get_bottom_left(&self) -> State {
         let mut new_table = vec![];
-        for col in self.table[self.size - 3..self.size].iter() {
-            new_table.push(col[self.size - 3..self.size].to_vec());
+        for col in self.table[self.height - 3..self.height].iter() {
+            new_table.push(col[self.width - 3..self.width].to_vec());
         }
         println!(
             "return : {:?}",
             State::new(
-                self.x - self.size + 3,
-                self.y - self.size + 3,
+                self.x - self.height + 3,
+                self.y - self.width + 3,
                 new_table.clone(),
-                self.size,
             )
+            .unwrap()
         );
-        return State::new(
-            self.x - self.size + 3,
-            self.y - self.size + 3,
+        State::new(
+            self.x - self.height + 3,
+            self.y - self.width + 3,
             new_table,
-            self.size,
-        );
+        )
+        .unwrap()
     }
 }
-impl DistNode<(i32, i32)> for State {
-    fn dist_from_end(&self) -> i32 {
-        let mut sm = 10;
-        for x in 0..self.size {
-            for y in 0..self.size {
-                if y*self.size+x+1 == self.table[x][y]{
-                    sm-=1;
+impl FromStr for State {
+    type Err = ParseError;
+    /// Reads a state in any of the notations of the *parse* module.
+    fn from_str(s: &str) -> Result<State, ParseError> {
+        parse_state(s)
+    }
+}
+
+impl fmt::Display for State {
+    /// Writes the state in the grid notation : `1.2.3:4.5.6:7.0.8`,
+    /// or in the lines notation with the alternate flag : `{:#}`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (cells, rows) = if f.alternate() { (" ", "\n") } else { (".", ":") };
+        let width = if f.alternate() {
+            (self.height * self.width - 1).to_string().len()
+        } else {
+            0
+        };
+        for (x, row) in self.table.iter().enumerate() {
+            if x > 0 {
+                f.write_str(rows)?;
+            }
+            for (y, value) in row.iter().enumerate() {
+                if y > 0 {
+                    f.write_str(cells)?;
                 }
+                write!(f, "{:>1$}", value, width)?;
             }
         }
-        sm
+        Ok(())
+    }
+}
+
+impl Board for State {
+    fn height(&self) -> usize {
+        self.height
+    }
+    fn width(&self) -> usize {
+        self.width
+    }
+    fn cell(&self, x: usize, y: usize) -> usize {
+        self.table[x][y]
+    }
+    fn goal(&self) -> &Goal {
+        &self.goal
+    }
+}
+
+impl DistNode<Direction> for State {
+    fn dist_from_end(&self) -> i32 {
+        self.heuristic.estimate(self)
     }
 
-    fn moves(&self) -> Vec<(i32, i32)> {
+    fn moves(&self) -> Vec<Direction> {
         self.moves()
     }
 
-    fn modify(&mut self, mv: &(i32, i32)) {
+    fn modify(&mut self, mv: &Direction) {
         self.modify(*mv);
     }
     fn end(&self) -> bool {
-        self.is_final(false)
+        self.is_final()
     }
-    fn cost_to(&self, target: &Self) -> i32 {
+    fn cost_to(&self, _target: &Self) -> i32 {
         1
     }
+    fn memory_size(&self) -> usize {
+        let row = size_of::<Vec<usize>>() + self.width * size_of::<usize>();
+        size_of::<Self>() + self.height * row
+    }
+}
+
+impl ReversibleNode<Direction> for State {
+    fn reverse_move(&self, mv: &Direction) -> Direction {
+        mv.inverse()
+    }
+    fn final_node(&self) -> Self {
+        let mut state = State::new_solved(self.goal().clone());
+        state.set_heuristic(self.heuristic.clone());
+        state
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn solvability() {
+        let dims = [(2, 2), (3, 3), (4, 4), (5, 5), (2, 3), (3, 2), (3, 4), (4, 3), (4, 6)];
+        let goals = ["standard", "blank-first", "snake", "spiral", "column-major"];
+        for (&(height, width), name) in dims.iter().zip(goals.iter().cycle()) {
+            let mut state = State::new_solved(Goal::from_name(name, height, width).unwrap());
+            assert!(state.is_solvable());
+            for seed in 0..20 {
+                state.shuffle(7, seed);
+                assert!(state.is_solvable());
+                // swapping two tiles changes the parity of the permutation.
+                let mut swapped = state.clone();
+                let row = if state.x == 0 { 1 } else { 0 };
+                swapped.table[row].swap(0, 1);
+                assert!(!swapped.is_solvable(), "{:?}", swapped);
+            }
+        }
+    }
 }